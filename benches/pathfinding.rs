@@ -0,0 +1,73 @@
+// Compare, sur une grande carte, trois façons de trouver le chemin des robots : une recherche
+// complète à chaque pas, D* Lite relancé à chaque pas en reprenant sa recherche précédente, et
+// les chemins gardés tant qu'ils restent valables
+//
+// cargo bench --bench pathfinding
+use rust_game::config::{RobotConfig, SimulationConfig};
+use rust_game::simulation::Simulation;
+use std::time::{Duration, Instant};
+
+const TICKS: usize = 3000;
+
+#[derive(Clone, Copy)]
+enum Replanning {
+    Fresh,
+    Incremental,
+    Cached,
+}
+
+fn run(config: &SimulationConfig, replanning: Replanning) -> (Duration, Simulation) {
+    let mut simulation = Simulation::new(config).unwrap();
+    simulation.verbose = false;
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        match replanning {
+            Replanning::Fresh => simulation.forget_paths(),
+            Replanning::Incremental => {
+                for robot in &mut simulation.robots {
+                    robot.path.clear();
+                }
+            }
+            Replanning::Cached => {}
+        }
+        simulation.step();
+    }
+    (start.elapsed(), simulation)
+}
+
+fn main() {
+    let config = SimulationConfig {
+        seed: 1,
+        map_width: 250,
+        map_height: 250,
+        energy_count: 80,
+        crystal_count: 160,
+        robots: vec![RobotConfig::default(); 12],
+        ..SimulationConfig::default()
+    };
+    println!(
+        "Carte {}x{}, {} robots, {} ticks",
+        config.map_width,
+        config.map_height,
+        config.robots.len(),
+        TICKS
+    );
+    let (fresh, fresh_end) = run(&config, Replanning::Fresh);
+    let (incremental, incremental_end) = run(&config, Replanning::Incremental);
+    let (cached, cached_end) = run(&config, Replanning::Cached);
+    // Le chemin trouvé ne dépend pas des recherches précédentes : c'est la même partie
+    assert_eq!(incremental_end.state_hash(), fresh_end.state_hash());
+    for (name, elapsed, simulation) in [
+        ("recherche complète à chaque pas", fresh, fresh_end),
+        ("D* Lite repris à chaque pas", incremental, incremental_end),
+        ("chemins gardés", cached, cached_end),
+    ] {
+        println!(
+            "{:<32} {:>7.3} s  x{:<5.1} {} ressources",
+            name,
+            elapsed.as_secs_f64(),
+            fresh.as_secs_f64() / elapsed.as_secs_f64(),
+            simulation.crystal_score + simulation.energy_score
+        );
+    }
+}
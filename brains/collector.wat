@@ -0,0 +1,52 @@
+;; Contrôleur d'exemple au format texte WebAssembly. Un module compilé depuis un autre
+;; langage (.wasm) se dépose dans ce dossier de la même façon.
+;; Rentre dès qu'une ressource est repérée, s'approche des ressources libres visibles
+;; et explore le brouillard le reste du temps.
+(module
+  (import "robot" "read_robot" (func $read_robot (param i32) (result i32)))
+  (import "robot" "read_sensors" (func $read_sensors (param i32) (result i32)))
+  (import "robot" "move_to" (func $move_to (param i32 i32)))
+  (import "robot" "pick_up" (func $pick_up))
+  (import "robot" "deposit" (func $deposit))
+  (import "robot" "explore" (func $explore))
+  (import "robot" "return_to_base" (func $return_to_base))
+  (memory (export "memory") 1)
+
+  ;; État du robot à l'adresse 0 (8 entiers), grille de capteurs à l'adresse 64
+  (func (export "tick")
+    (local $side i32) (local $radius i32) (local $index i32) (local $cell i32)
+    (drop (call $read_robot (i32.const 0)))
+
+    ;; Extracteur : dépose sa charge, sinon va chercher la ressource
+    (if (i32.eq (i32.load (i32.const 8)) (i32.const 1))
+      (then
+        (if (i32.load (i32.const 12))
+          (then (call $deposit))
+          (else (call $pick_up)))
+        (return)))
+
+    ;; Ressource repérée : retour à la base
+    (if (i32.ge_s (i32.load (i32.const 16)) (i32.const 0))
+      (then
+        (call $return_to_base)
+        (return)))
+
+    (local.set $side (call $read_sensors (i32.const 64)))
+    (local.set $radius (i32.div_u (local.get $side) (i32.const 2)))
+    (block $done
+      (loop $cells
+        (br_if $done
+          (i32.ge_u (local.get $index) (i32.mul (local.get $side) (local.get $side))))
+        (local.set $cell (i32.load8_u (i32.add (i32.const 64) (local.get $index))))
+        ;; 3 : énergie, 4 : cristal
+        (if (i32.or (i32.eq (local.get $cell) (i32.const 3))
+                    (i32.eq (local.get $cell) (i32.const 4)))
+          (then
+            (call $move_to
+              (i32.sub (i32.rem_u (local.get $index) (local.get $side)) (local.get $radius))
+              (i32.sub (i32.div_u (local.get $index) (local.get $side)) (local.get $radius)))
+            (return)))
+        (local.set $index (i32.add (local.get $index) (i32.const 1)))
+        (br $cells)))
+    (call $explore))
+)
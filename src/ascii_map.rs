@@ -0,0 +1,295 @@
+use crate::config::ConfigError;
+use crate::simulation::{Cell, Terrain};
+
+// Carte dessinée à la main : # obstacle, E énergie, C cristal, B base, . vide.
+// Les cases vides peuvent préciser leur terrain : = route, : sable, ^ roche, ~ boue.
+// Après une ligne vide, une deuxième grille de même taille peut donner le terrain de toutes
+// les cases, y compris sous les obstacles, les ressources et la base
+pub fn cell_char(cell: &Cell, terrain: Terrain) -> char {
+    match cell {
+        Cell::Empty => match terrain {
+            Terrain::Road => '=',
+            Terrain::Plain => '.',
+            Terrain::Sand => ':',
+            Terrain::Rock => '^',
+            Terrain::Mud => '~',
+        },
+        Cell::Obstacle => '#',
+        Cell::Energy | Cell::ReservedEnergy => 'E',
+        Cell::Crystal | Cell::ReservedCrystal => 'C',
+        Cell::Base => 'B',
+    }
+}
+
+fn terrain_char(terrain: Terrain) -> char {
+    cell_char(&Cell::Empty, terrain)
+}
+
+fn char_terrain(character: char) -> Option<Terrain> {
+    match char_cell(character)? {
+        (Cell::Empty, terrain) => Some(terrain),
+        _ => None,
+    }
+}
+
+fn char_cell(character: char) -> Option<(Cell, Terrain)> {
+    match character {
+        '.' => Some((Cell::Empty, Terrain::Plain)),
+        '=' => Some((Cell::Empty, Terrain::Road)),
+        ':' => Some((Cell::Empty, Terrain::Sand)),
+        '^' => Some((Cell::Empty, Terrain::Rock)),
+        '~' => Some((Cell::Empty, Terrain::Mud)),
+        '#' => Some((Cell::Obstacle, Terrain::Plain)),
+        'E' => Some((Cell::Energy, Terrain::Plain)),
+        'C' => Some((Cell::Crystal, Terrain::Plain)),
+        'B' => Some((Cell::Base, Terrain::Plain)),
+        _ => None,
+    }
+}
+
+// La grille du terrain n'est écrite que si la première ne suffit pas : quand une case
+// autre que vide n'est pas en plaine
+pub fn to_ascii(map: &[Vec<Cell>], terrain: &[Vec<Terrain>]) -> String {
+    let grid = |char_of: &dyn Fn(&Cell, Terrain) -> char| -> String {
+        map.iter()
+            .zip(terrain)
+            .map(|(row, terrain_row)| {
+                row.iter()
+                    .zip(terrain_row)
+                    .map(|(cell, &terrain)| char_of(cell, terrain))
+                    .chain(['\n'])
+                    .collect::<String>()
+            })
+            .collect()
+    };
+    let hidden_terrain = map
+        .iter()
+        .flatten()
+        .zip(terrain.iter().flatten())
+        .any(|(cell, &terrain)| *cell != Cell::Empty && terrain != Terrain::Plain);
+    let mut text = grid(&cell_char);
+    if hidden_terrain {
+        text.push('\n');
+        text.push_str(&grid(&|_, terrain| terrain_char(terrain)));
+    }
+    text
+}
+
+// Carte lue ou générée : contenu des cases, terrain et position de la base
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldMap {
+    pub map: Vec<Vec<Cell>>,
+    pub terrain: Vec<Vec<Terrain>>,
+    pub base_position: (usize, usize),
+}
+
+pub fn parse(text: &str) -> Result<WorldMap, ConfigError> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim_end()).collect();
+    let lines = match lines.iter().rposition(|line| !line.is_empty()) {
+        Some(last) => &lines[..=last],
+        None => return Err(ConfigError::Invalid("la carte ASCII est vide".to_string())),
+    };
+    let (lines, terrain_lines) = match lines.iter().position(|line| line.is_empty()) {
+        Some(blank) => (&lines[..blank], Some(&lines[blank + 1..])),
+        None => (lines, None),
+    };
+
+    let width = lines[0].chars().count();
+    let mut map = Vec::with_capacity(lines.len());
+    let mut terrain = Vec::with_capacity(lines.len());
+    let mut bases = Vec::new();
+    for (y, line) in lines.iter().enumerate() {
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, character)| {
+                let (cell, terrain) = char_cell(character).ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "caractère '{}' inconnu ligne {} colonne {} de la carte ASCII",
+                        character,
+                        y + 1,
+                        x + 1
+                    ))
+                })?;
+                if cell == Cell::Base {
+                    bases.push((x, y));
+                }
+                Ok((cell, terrain))
+            })
+            .collect::<Result<Vec<(Cell, Terrain)>, ConfigError>>()?;
+        if row.len() != width {
+            return Err(ConfigError::Invalid(format!(
+                "la ligne {} de la carte ASCII fait {} cases au lieu de {}",
+                y + 1,
+                row.len(),
+                width
+            )));
+        }
+        let (cells, terrain_row) = row.into_iter().unzip();
+        map.push(cells);
+        terrain.push(terrain_row);
+    }
+
+    if width < 3 || map.len() < 3 {
+        return Err(ConfigError::Invalid(format!(
+            "la carte ASCII doit faire au moins 3x3 cases (reçu {}x{})",
+            width,
+            map.len()
+        )));
+    }
+    if let Some(terrain_lines) = terrain_lines {
+        parse_terrain(terrain_lines, &map, &mut terrain)?;
+    }
+    match bases[..] {
+        [base_position] => Ok(WorldMap {
+            map,
+            terrain,
+            base_position,
+        }),
+        _ => Err(ConfigError::Invalid(format!(
+            "la carte ASCII doit contenir exactement une base 'B' (trouvé {})",
+            bases.len()
+        ))),
+    }
+}
+
+// Grille du terrain, qui doit s'accorder avec le terrain déjà donné par les cases vides
+fn parse_terrain(
+    lines: &[&str],
+    map: &[Vec<Cell>],
+    terrain: &mut [Vec<Terrain>],
+) -> Result<(), ConfigError> {
+    if lines.len() != map.len() {
+        return Err(ConfigError::Invalid(format!(
+            "la grille du terrain fait {} lignes au lieu de {}",
+            lines.len(),
+            map.len()
+        )));
+    }
+    for (y, (line, terrain_row)) in lines.iter().zip(terrain.iter_mut()).enumerate() {
+        if line.chars().count() != terrain_row.len() {
+            return Err(ConfigError::Invalid(format!(
+                "la ligne {} de la grille du terrain fait {} cases au lieu de {}",
+                y + 1,
+                line.chars().count(),
+                terrain_row.len()
+            )));
+        }
+        for (x, character) in line.chars().enumerate() {
+            let value = char_terrain(character).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "terrain '{}' inconnu ligne {} colonne {} de la grille du terrain",
+                    character,
+                    y + 1,
+                    x + 1
+                ))
+            })?;
+            if map[y][x] == Cell::Empty && terrain_row[x] != value {
+                return Err(ConfigError::Invalid(format!(
+                    "le terrain ligne {} colonne {} contredit celui de la carte",
+                    y + 1,
+                    x + 1
+                )));
+            }
+            terrain_row[x] = value;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAZE: &str = "\
+#######
+#B..#E#
+#.#.#.#
+#.#...#
+#C#####
+";
+
+    #[test]
+    fn test_parse_ascii_map() {
+        let WorldMap {
+            map,
+            base_position: base,
+            ..
+        } = parse(MAZE).unwrap();
+        assert_eq!(base, (1, 1));
+        assert_eq!(map.len(), 5);
+        assert_eq!(map[0].len(), 7);
+        assert_eq!(map[1][5], Cell::Energy);
+        assert_eq!(map[4][1], Cell::Crystal);
+        assert_eq!(map[2][2], Cell::Obstacle);
+        assert_eq!(map[3][3], Cell::Empty);
+    }
+
+    #[test]
+    fn test_ascii_round_trip() {
+        let world = parse(MAZE).unwrap();
+        assert_eq!(to_ascii(&world.map, &world.terrain), MAZE);
+    }
+
+    #[test]
+    fn test_parse_terrain() {
+        let world = parse("#####\n#B=:#\n#^~C#\n#####\n").unwrap();
+        assert_eq!(world.map[1][2], Cell::Empty);
+        assert_eq!(world.terrain[1][2], Terrain::Road);
+        assert_eq!(world.terrain[1][3], Terrain::Sand);
+        assert_eq!(world.terrain[2][1], Terrain::Rock);
+        assert_eq!(world.terrain[2][2], Terrain::Mud);
+        assert_eq!(world.terrain[2][3], Terrain::Plain);
+        assert_eq!(
+            to_ascii(&world.map, &world.terrain),
+            "#####\n#B=:#\n#^~C#\n#####\n"
+        );
+    }
+
+    #[test]
+    fn test_terrain_under_resources_round_trips() {
+        let text = "#####\n#B=:#\n#^~C#\n#####\n\n^^^^^\n~~=:^\n^^~:^\n.....\n";
+        let world = parse(text).unwrap();
+        assert_eq!(world.map[1][1], Cell::Base);
+        assert_eq!(world.terrain[1][1], Terrain::Mud);
+        assert_eq!(world.terrain[2][3], Terrain::Sand);
+        assert_eq!(world.terrain[0][0], Terrain::Rock);
+        assert_eq!(to_ascii(&world.map, &world.terrain), text);
+    }
+
+    #[test]
+    fn test_generated_map_round_trips() {
+        use crate::config::SimulationConfig;
+        use crate::simulation::Simulation;
+        let simulation = Simulation::new(&SimulationConfig::default()).unwrap();
+        let world = parse(&to_ascii(&simulation.map, &simulation.terrain)).unwrap();
+        assert_eq!(world.map, simulation.map);
+        assert_eq!(world.terrain, simulation.terrain);
+        assert_eq!(world.base_position, simulation.base_position);
+    }
+
+    #[test]
+    fn test_trailing_blank_lines_and_crlf_are_accepted() {
+        let text = MAZE.replace('\n', "\r\n") + "\r\n\r\n";
+        assert_eq!(parse(&text).unwrap().map, parse(MAZE).unwrap().map);
+    }
+
+    #[test]
+    fn test_invalid_ascii_maps_are_rejected() {
+        for (text, message) in [
+            ("###\n#B?\n###\n", "caractère '?' inconnu ligne 2 colonne 3"),
+            ("###\n#B\n###\n", "la ligne 2"),
+            ("###\n#.#\n###\n", "trouvé 0"),
+            ("###\n#BB\n###\n", "trouvé 2"),
+            ("#B\n##\n", "au moins 3x3"),
+            ("\n\n", "vide"),
+            ("###\n#B#\n###\n\n...\n...\n", "2 lignes au lieu de 3"),
+            ("###\n#B#\n###\n\n...\n..\n...\n", "la ligne 2 de la grille"),
+            ("###\n#B#\n###\n\n...\n.#.\n...\n", "terrain '#' inconnu"),
+            ("###\n#B.\n###\n\n...\n..~\n...\n", "contredit"),
+        ] {
+            let err = parse(text).unwrap_err().to_string();
+            assert!(err.contains(message), "{}: {}", text, err);
+        }
+    }
+}
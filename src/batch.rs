@@ -0,0 +1,360 @@
+use crate::config::SimulationConfig;
+use crate::render;
+use crate::simulation::{EndReason, Simulation};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+// Nombre de ticks entre deux mesures de la couverture du brouillard
+pub const COVERAGE_INTERVAL: u64 = 100;
+
+// Résultat d'une partie du batch
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SeedResult {
+    pub seed: u64,
+    pub ticks: u64,
+    // Toutes les ressources ont été ramenées à la base
+    pub completed: bool,
+    pub crystal_score: u32,
+    pub energy_score: u32,
+    pub distance_travelled: u64,
+    pub idle_ticks: u64,
+    // Part de la carte découverte en fin de partie
+    pub coverage: f64,
+    // Couverture au tick 0 puis tous les COVERAGE_INTERVAL ticks
+    pub coverage_over_time: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MetricSummary {
+    pub mean: f64,
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub runs: usize,
+    pub completed: usize,
+    pub ticks: MetricSummary,
+    pub crystal_score: MetricSummary,
+    pub energy_score: MetricSummary,
+    pub distance_travelled: MetricSummary,
+    pub idle_ticks: MetricSummary,
+    pub coverage: MetricSummary,
+}
+
+// Miniatures PNG de l'état final, une par seed
+#[derive(Clone, Debug)]
+pub struct Thumbnails {
+    pub dir: PathBuf,
+    pub pixels_per_cell: u32,
+}
+
+#[derive(Serialize)]
+struct BatchReport<'a> {
+    summary: &'a BatchSummary,
+    runs: &'a [SeedResult],
+}
+
+// Fait tourner une partie silencieuse par seed, en parallèle sur tous les coeurs.
+// Les seeds dont la carte ne peut pas être générée sont renvoyées avec leur erreur.
+pub fn run_batch(
+    config: &SimulationConfig,
+    seeds: Range<u64>,
+    thumbnails: Option<&Thumbnails>,
+) -> (Vec<SeedResult>, Vec<(u64, String)>) {
+    let outcomes: Vec<Result<SeedResult, (u64, String)>> = seeds
+        .into_par_iter()
+        .map(|seed| run_seed(config, seed, thumbnails).map_err(|err| (seed, err)))
+        .collect();
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for outcome in outcomes {
+        match outcome {
+            Ok(result) => results.push(result),
+            Err(err) => errors.push(err),
+        }
+    }
+    (results, errors)
+}
+
+fn run_seed(
+    config: &SimulationConfig,
+    seed: u64,
+    thumbnails: Option<&Thumbnails>,
+) -> Result<SeedResult, String> {
+    let config = SimulationConfig {
+        seed,
+        ..config.clone()
+    };
+    let mut simulation = Simulation::new(&config).map_err(|err| err.to_string())?;
+    simulation.verbose = false;
+    let mut coverage_over_time = vec![simulation.coverage()];
+    while !simulation.game_over {
+        simulation.step();
+        if simulation.tick.is_multiple_of(COVERAGE_INTERVAL) {
+            coverage_over_time.push(simulation.coverage());
+        }
+    }
+    if let Some(thumbnails) = thumbnails {
+        let path = thumbnails.dir.join(format!("seed_{}.png", seed));
+        render::save_png(&simulation, &path, thumbnails.pixels_per_cell)
+            .map_err(|err| format!("miniature {} impossible: {}", path.display(), err))?;
+    }
+
+    Ok(SeedResult {
+        seed,
+        ticks: simulation.tick,
+        completed: simulation.end_reason == Some(EndReason::AllResourcesCollected),
+        crystal_score: simulation.crystal_score,
+        energy_score: simulation.energy_score,
+        distance_travelled: simulation.stats.distance_travelled,
+        idle_ticks: simulation.stats.idle_ticks,
+        coverage: simulation.coverage(),
+        coverage_over_time,
+    })
+}
+
+pub fn summarize(results: &[SeedResult]) -> BatchSummary {
+    let metric = |value: fn(&SeedResult) -> f64| {
+        MetricSummary::from_values(results.iter().map(value).collect())
+    };
+
+    BatchSummary {
+        runs: results.len(),
+        completed: results.iter().filter(|result| result.completed).count(),
+        ticks: metric(|result| result.ticks as f64),
+        crystal_score: metric(|result| result.crystal_score as f64),
+        energy_score: metric(|result| result.energy_score as f64),
+        distance_travelled: metric(|result| result.distance_travelled as f64),
+        idle_ticks: metric(|result| result.idle_ticks as f64),
+        coverage: metric(|result| result.coverage),
+    }
+}
+
+impl MetricSummary {
+    fn from_values(mut values: Vec<f64>) -> MetricSummary {
+        if values.is_empty() {
+            return MetricSummary {
+                mean: 0.0,
+                min: 0.0,
+                p50: 0.0,
+                p90: 0.0,
+                p99: 0.0,
+                max: 0.0,
+            };
+        }
+
+        values.sort_by(|a, b| a.total_cmp(b));
+        MetricSummary {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            min: values[0],
+            p50: percentile(&values, 50.0),
+            p90: percentile(&values, 90.0),
+            p99: percentile(&values, 99.0),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+// Percentile au rang le plus proche sur des valeurs triées
+fn percentile(sorted_values: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted_values.len() as f64).ceil() as usize;
+    sorted_values[rank.clamp(1, sorted_values.len()) - 1]
+}
+
+pub fn write_runs_csv(results: &[SeedResult], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        writer,
+        "seed,ticks,completed,crystal_score,energy_score,distance_travelled,idle_ticks,coverage"
+    )?;
+    for result in results {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{}",
+            result.seed,
+            result.ticks,
+            result.completed,
+            result.crystal_score,
+            result.energy_score,
+            result.distance_travelled,
+            result.idle_ticks,
+            result.coverage
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_summary_csv(summary: &BatchSummary, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "metric,mean,min,p50,p90,p99,max")?;
+    for (name, metric) in [
+        ("ticks", &summary.ticks),
+        ("crystal_score", &summary.crystal_score),
+        ("energy_score", &summary.energy_score),
+        ("distance_travelled", &summary.distance_travelled),
+        ("idle_ticks", &summary.idle_ticks),
+        ("coverage", &summary.coverage),
+    ] {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            name, metric.mean, metric.min, metric.p50, metric.p90, metric.p99, metric.max
+        )?;
+    }
+    Ok(())
+}
+
+// Couverture moyenne, minimale et maximale au fil des ticks. Une partie terminée
+// garde sa couverture finale jusqu'à la fin de la plus longue
+pub fn write_coverage_csv(results: &[SeedResult], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "tick,mean,min,max")?;
+    let samples = results
+        .iter()
+        .map(|result| result.coverage_over_time.len())
+        .max()
+        .unwrap_or(0);
+    for sample in 0..samples {
+        let values: Vec<f64> = results
+            .iter()
+            .filter_map(|result| {
+                let series = &result.coverage_over_time;
+                series.get(sample).or(series.last()).copied()
+            })
+            .collect();
+        let metric = MetricSummary::from_values(values);
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            sample as u64 * COVERAGE_INTERVAL,
+            metric.mean,
+            metric.min,
+            metric.max
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_json(
+    results: &[SeedResult],
+    summary: &BatchSummary,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let report = BatchReport {
+        summary,
+        runs: results,
+    };
+    serde_json::to_writer_pretty(&mut *writer, &report)?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EndConditions;
+    use crate::simulation::Exploration;
+
+    fn result(seed: u64, ticks: u64) -> SeedResult {
+        SeedResult {
+            seed,
+            ticks,
+            completed: true,
+            crystal_score: 0,
+            energy_score: 0,
+            distance_travelled: 0,
+            idle_ticks: 0,
+            coverage: 0.0,
+            coverage_over_time: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let values: Vec<f64> = (1..=10).map(|v| v as f64).collect();
+        assert_eq!(percentile(&values, 50.0), 5.0);
+        assert_eq!(percentile(&values, 90.0), 9.0);
+        assert_eq!(percentile(&values, 99.0), 10.0);
+        assert_eq!(percentile(&[3.0], 50.0), 3.0);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let results: Vec<SeedResult> = (0..4).map(|seed| result(seed, (seed + 1) * 10)).collect();
+        let summary = summarize(&results);
+        assert_eq!(summary.runs, 4);
+        assert_eq!(summary.completed, 4);
+        assert_eq!(summary.ticks.mean, 25.0);
+        assert_eq!(summary.ticks.min, 10.0);
+        assert_eq!(summary.ticks.p50, 20.0);
+        assert_eq!(summary.ticks.max, 40.0);
+    }
+
+    #[test]
+    fn test_run_batch_matches_single_runs() {
+        let config = SimulationConfig {
+            end: EndConditions {
+                max_ticks: Some(300),
+                ..EndConditions::default()
+            },
+            ..SimulationConfig::default()
+        };
+        let (results, errors) = run_batch(&config, 0..8, None);
+        assert!(errors.is_empty());
+        assert_eq!(
+            results.iter().map(|result| result.seed).collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+        assert_eq!(results[3], run_seed(&config, 3, None).unwrap());
+        assert!(results.iter().all(|result| result.ticks <= 300));
+    }
+
+    #[test]
+    fn test_write_runs_csv() {
+        let mut output = Vec::new();
+        write_runs_csv(&[result(7, 12)], &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "seed,ticks,completed,crystal_score,energy_score,distance_travelled,idle_ticks,coverage\n\
+             7,12,true,0,0,0,0,0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_coverage_csv() {
+        let mut short = result(0, 100);
+        short.coverage_over_time = vec![0.0, 0.5];
+        let mut long = result(1, 250);
+        long.coverage_over_time = vec![0.0, 0.25, 0.75];
+        let mut output = Vec::new();
+        write_coverage_csv(&[short, long], &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "tick,mean,min,max\n0,0,0,0\n100,0.375,0.25,0.5\n200,0.625,0.5,0.75\n"
+        );
+    }
+
+    #[test]
+    fn test_frontier_exploration_covers_more_than_random_walk() {
+        let coverage = |exploration| {
+            let config = SimulationConfig {
+                exploration,
+                end: EndConditions {
+                    max_ticks: Some(400),
+                    ..EndConditions::default()
+                },
+                energy_count: 0,
+                crystal_count: 1,
+                ..SimulationConfig::default()
+            };
+            let (results, _) = run_batch(&config, 0..6, None);
+            summarize(&results).coverage.mean
+        };
+        assert!(coverage(Exploration::Frontier) > coverage(Exploration::Random));
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+// Modèle de batterie des robots. Sans section [battery] dans le scénario, les robots
+// se déplacent sans limite
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Battery {
+    // Charge d'un robot neuf ou rechargé à fond
+    #[serde(default = "Battery::default_capacity")]
+    pub capacity: u32,
+    // Charge dépensée par case parcourue
+    #[serde(default = "Battery::default_move_cost")]
+    pub move_cost: u32,
+    // En plus par case quand le robot porte une ressource ou remorque une épave
+    #[serde(default = "Battery::default_load_cost")]
+    pub load_cost: u32,
+    // Charge rendue par unité d'énergie dépensée à la base
+    #[serde(default = "Battery::default_charge_per_energy")]
+    pub charge_per_energy: u32,
+    // Marge gardée pour les détours du retour à la base
+    #[serde(default = "Battery::default_reserve")]
+    pub reserve: u32,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Battery {
+            capacity: Battery::default_capacity(),
+            move_cost: Battery::default_move_cost(),
+            load_cost: Battery::default_load_cost(),
+            charge_per_energy: Battery::default_charge_per_energy(),
+            reserve: Battery::default_reserve(),
+        }
+    }
+}
+
+impl Battery {
+    fn default_capacity() -> u32 {
+        300
+    }
+
+    fn default_move_cost() -> u32 {
+        1
+    }
+
+    fn default_load_cost() -> u32 {
+        1
+    }
+
+    fn default_charge_per_energy() -> u32 {
+        150
+    }
+
+    fn default_reserve() -> u32 {
+        10
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity == 0 || self.charge_per_energy == 0 {
+            return Err(
+                "la capacité et la recharge de la batterie doivent être strictement positives"
+                    .to_string(),
+            );
+        }
+        if self.reserve >= self.capacity {
+            return Err(format!(
+                "la réserve de la batterie ({}) doit être inférieure à sa capacité ({})",
+                self.reserve, self.capacity
+            ));
+        }
+        Ok(())
+    }
+
+    // Une unité d'énergie n'est dépensée que si toute sa charge tient dans la batterie,
+    // sauf pour un robot tombé sous sa réserve
+    pub fn worth_charging(&self, charge: u32) -> bool {
+        charge <= self.reserve || charge + self.charge_per_energy <= self.capacity
+    }
+
+    // Coût d'une case, chargé ou non
+    pub fn step_cost(&self, loaded: bool) -> u32 {
+        if loaded {
+            self.move_cost + self.load_cost
+        } else {
+            self.move_cost
+        }
+    }
+
+    // Le robot doit rentrer : il lui reste juste de quoi parcourir `steps` cases, plus la réserve
+    pub fn must_return(&self, charge: u32, steps: usize, loaded: bool) -> bool {
+        charge as usize <= steps * self.step_cost(loaded) as usize + self.reserve as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loaded_robot_returns_earlier() {
+        let battery = Battery::default();
+        assert_eq!(battery.step_cost(false), 1);
+        assert_eq!(battery.step_cost(true), 2);
+        assert!(!battery.must_return(40, 20, false));
+        assert!(battery.must_return(40, 20, true));
+        assert!(battery.must_return(10, 0, false));
+    }
+
+    #[test]
+    fn test_energy_is_not_wasted() {
+        let battery = Battery::default();
+        assert!(battery.worth_charging(5));
+        assert!(battery.worth_charging(150));
+        assert!(!battery.worth_charging(151));
+    }
+
+    #[test]
+    fn test_invalid_battery_is_rejected() {
+        assert!(Battery::default().validate().is_ok());
+        for battery in [
+            Battery {
+                capacity: 0,
+                ..Battery::default()
+            },
+            Battery {
+                reserve: 300,
+                ..Battery::default()
+            },
+            Battery {
+                charge_per_energy: 0,
+                ..Battery::default()
+            },
+        ] {
+            assert!(battery.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::exploration;
+use crate::simulation::{Exploration, Robot, Role, Simulation};
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+type Position = (usize, usize);
+
+// Ce qu'un robot prêt à agir demande à la simulation pour ce tick. Le ramassage, le dépôt
+// et les changements de rôle restent des règles du jeu appliquées après le déplacement
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Stay,
+    // Reste sur place et dévoile le brouillard dans un rayon de SCAN_RADIUS cases, hors des
+    // cases cachées par les obstacles
+    Scan,
+    // Case voisine, ignorée si elle n'est pas atteignable en un pas
+    Step(Position),
+    // Pathfinding de la simulation jusqu'à la cible, en gardant le chemin d'un tick à l'autre
+    GoTo { target: Position, avoid_fog: bool },
+    // Comme GoTo par les cases découvertes, la cible est annoncée aux autres explorateurs
+    GoToFrontier(Position),
+    // Descend le champ de distances partagé vers la base
+    ReturnToBase { avoid_fog: bool },
+}
+
+// Ce qu'un robot voit du monde au moment de décider
+pub struct WorldView<'a> {
+    pub simulation: &'a Simulation,
+    // Cases connues du robot : sa propre carte avec une portée radio, sinon celle de la base
+    pub known: &'a [Vec<bool>],
+    // Frontières visées par les autres explorateurs
+    pub claimed_frontiers: &'a [Position],
+}
+
+// Stratégie d'un robot. Toute décision aléatoire doit passer par `rng`,
+// le générateur de la simulation, pour qu'une seed reproduise la partie
+pub trait Behaviour: Send + Sync {
+    fn decide(&self, robot: &Robot, world: &WorldView, rng: &mut dyn RngCore) -> Command;
+}
+
+pub const DEFAULT_STRATEGY: &str = "default";
+
+// Stratégies qu'un scénario peut donner à ses robots, par nom
+const STRATEGIES: &[(&str, &dyn Behaviour)] = &[
+    (DEFAULT_STRATEGY, &DefaultBehaviour),
+    ("random", &RandomWalk),
+];
+
+pub fn strategy(name: &str) -> Option<&'static dyn Behaviour> {
+    STRATEGIES
+        .iter()
+        .find(|(strategy, _)| *strategy == name)
+        .map(|&(_, behaviour)| behaviour)
+}
+
+pub fn strategy_names() -> Vec<&'static str> {
+    STRATEGIES.iter().map(|&(name, _)| name).collect()
+}
+
+// Stratégies écrites hors de la crate, par nom. Elles sont passées à la simulation par
+// SimulationConfig::strategies et s'ajoutent aux stratégies intégrées. Les snapshots et les
+// replays n'en gardent que le nom : il faut les enregistrer à nouveau pour les relire
+#[derive(Clone, Default)]
+pub struct Strategies(BTreeMap<String, Arc<dyn Behaviour>>);
+
+impl Strategies {
+    pub fn register(&mut self, name: impl Into<String>, behaviour: Box<dyn Behaviour>) {
+        self.0.insert(name.into(), Arc::from(behaviour));
+    }
+
+    // Stratégie intégrée ou enregistrée
+    pub fn get(&self, name: &str) -> Option<&dyn Behaviour> {
+        strategy(name).or_else(|| self.0.get(name).map(|behaviour| behaviour.as_ref()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+// Le code des stratégies ne se compare pas : deux registres sont égaux s'ils ont les mêmes noms
+impl PartialEq for Strategies {
+    fn eq(&self, other: &Strategies) -> bool {
+        self.0.keys().eq(other.0.keys())
+    }
+}
+
+impl fmt::Debug for Strategies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.0.keys()).finish()
+    }
+}
+
+// Explorateurs qui cherchent des ressources selon le mode d'exploration du scénario et
+// rentrent les signaler, extracteurs qui vont les chercher par les cases découvertes
+pub struct DefaultBehaviour;
+
+impl Behaviour for DefaultBehaviour {
+    fn decide(&self, robot: &Robot, world: &WorldView, rng: &mut dyn RngCore) -> Command {
+        match robot.role {
+            Role::Explorer => match robot.resource_coords {
+                Some(_) => Command::ReturnToBase { avoid_fog: false },
+                None => match world.simulation.exploration {
+                    Exploration::Random => random_step(robot, world, rng),
+                    Exploration::Frontier => explore_frontier(robot, world, rng),
+                },
+            },
+            Role::Extractor => match (robot.resource_coords, &robot.carrying) {
+                (Some(target), None) => Command::GoTo {
+                    target,
+                    avoid_fog: true,
+                },
+                (Some(_), Some(_)) => Command::ReturnToBase { avoid_fog: true },
+                (None, _) => Command::Stay,
+            },
+        }
+    }
+}
+
+// Comme la stratégie par défaut, mais ses explorateurs marchent toujours au hasard
+pub struct RandomWalk;
+
+impl Behaviour for RandomWalk {
+    fn decide(&self, robot: &Robot, world: &WorldView, rng: &mut dyn RngCore) -> Command {
+        match (&robot.role, robot.resource_coords) {
+            (Role::Explorer, None) => random_step(robot, world, rng),
+            _ => DefaultBehaviour.decide(robot, world, rng),
+        }
+    }
+}
+
+pub fn random_step(robot: &Robot, world: &WorldView, mut rng: &mut dyn RngCore) -> Command {
+    let simulation = world.simulation;
+    Command::Step(Simulation::move_robot_randomly(
+        robot.x,
+        robot.y,
+        simulation.movement.directions(),
+        &mut rng,
+        &simulation.map,
+        simulation.topology,
+    ))
+}
+
+// Garde la frontière visée tant qu'elle touche le brouillard, sinon en choisit une autre
+pub fn frontier_target(robot: &Robot, world: &WorldView) -> Option<Position> {
+    let simulation = world.simulation;
+    robot
+        .frontier
+        .filter(|&frontier| exploration::is_frontier(simulation, world.known, frontier))
+        .or_else(|| {
+            exploration::nearest_frontier(
+                simulation,
+                world.known,
+                (robot.x, robot.y),
+                world.claimed_frontiers,
+            )
+        })
+}
+
+// Sans frontière atteignable (au début de la partie), le robot marche au hasard
+fn explore_frontier(robot: &Robot, world: &WorldView, rng: &mut dyn RngCore) -> Command {
+    frontier_target(robot, world)
+        .map_or_else(|| random_step(robot, world, rng), Command::GoToFrontier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RobotConfig, SimulationConfig};
+    use crate::simulation::Cell;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    fn simulation(robots: Vec<RobotConfig>) -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            robots,
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    fn decide(behaviour: &dyn Behaviour, simulation: &Simulation, robot: &Robot) -> Command {
+        let world = WorldView {
+            simulation,
+            known: &simulation.discovered,
+            claimed_frontiers: &[],
+        };
+        behaviour.decide(robot, &world, &mut ChaCha12Rng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn test_strategies_by_name() {
+        assert!(strategy(DEFAULT_STRATEGY).is_some());
+        assert!(strategy("random").is_some());
+        assert!(strategy("telepathe").is_none());
+        assert_eq!(strategy_names(), vec!["default", "random"]);
+    }
+
+    #[test]
+    fn test_registered_strategies_extend_builtins() {
+        let mut strategies = Strategies::default();
+        strategies.register("immobile", Box::new(RandomWalk));
+        assert!(strategies.get("immobile").is_some());
+        assert!(strategies.get(DEFAULT_STRATEGY).is_some());
+        assert!(strategies.get("telepathe").is_none());
+        assert_eq!(strategies.names().collect::<Vec<_>>(), vec!["immobile"]);
+    }
+
+    #[test]
+    fn test_default_behaviour_follows_robot_state() {
+        let simulation = simulation(vec![RobotConfig::default()]);
+        let mut robot = simulation.robots[0].clone();
+
+        robot.resource_coords = Some((3, 4));
+        assert_eq!(
+            decide(&DefaultBehaviour, &simulation, &robot),
+            Command::ReturnToBase { avoid_fog: false }
+        );
+        robot.role = Role::Extractor;
+        assert_eq!(
+            decide(&DefaultBehaviour, &simulation, &robot),
+            Command::GoTo {
+                target: (3, 4),
+                avoid_fog: true
+            }
+        );
+        robot.carrying = Some(Cell::Crystal);
+        assert_eq!(
+            decide(&DefaultBehaviour, &simulation, &robot),
+            Command::ReturnToBase { avoid_fog: true }
+        );
+    }
+
+    #[test]
+    fn test_random_walk_ignores_frontiers() {
+        let mut simulation = simulation(vec![RobotConfig::default()]);
+        simulation.discovered = vec![vec![true; 40]; 30];
+        let (x, y) = simulation.base_position;
+        simulation.discovered[y][x + 2] = false;
+        let robot = simulation.robots[0].clone();
+
+        assert!(matches!(
+            decide(&DefaultBehaviour, &simulation, &robot),
+            Command::GoToFrontier(_)
+        ));
+        assert!(matches!(
+            decide(&RandomWalk, &simulation, &robot),
+            Command::Step(_)
+        ));
+    }
+
+    #[test]
+    fn test_scenario_picks_strategy_per_robot() {
+        let mut simulation = simulation(vec![
+            RobotConfig::default(),
+            RobotConfig {
+                strategy: "random".to_string(),
+                ..RobotConfig::default()
+            },
+        ]);
+        assert_eq!(simulation.robots[0].strategy, "default");
+        assert_eq!(simulation.robots[1].strategy, "random");
+
+        // Le marcheur au hasard ne vise jamais de frontière
+        for _ in 0..200 {
+            simulation.step();
+            assert_eq!(simulation.robots[1].frontier, None);
+        }
+    }
+}
@@ -0,0 +1,471 @@
+use crate::behaviour::{self, Command, WorldView};
+use crate::config::ConfigError;
+use crate::simulation::{Robot, Role};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Noeud d'un arbre de comportement, écrit en JSON sous la forme {"sequence": [...]},
+// {"invert": {...}}, {"condition": "carrying"} ou {"action": "deposit"}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Node {
+    // Enfants dans l'ordre jusqu'au premier qui échoue
+    Sequence(Vec<Node>),
+    // Enfants dans l'ordre jusqu'au premier qui réussit
+    Selector(Vec<Node>),
+    // Décorateurs
+    Invert(Box<Node>),
+    ForceSuccess(Box<Node>),
+    Condition(Condition),
+    Action(Action),
+}
+
+impl Node {
+    fn children(&self) -> &[Node] {
+        match self {
+            Node::Sequence(children) | Node::Selector(children) => children,
+            Node::Invert(child) | Node::ForceSuccess(child) => std::slice::from_ref(&**child),
+            Node::Condition(_) | Node::Action(_) => &[],
+        }
+    }
+
+    // Nom du noeud dans le chemin affiché, avec l'enfant choisi pour les séquences et sélecteurs
+    fn label(&self, child: usize) -> String {
+        match self {
+            Node::Sequence(_) => format!("sequence[{}]", child),
+            Node::Selector(_) => format!("selector[{}]", child),
+            Node::Invert(_) => "invert".to_string(),
+            Node::ForceSuccess(_) => "force_success".to_string(),
+            Node::Condition(_) => "condition".to_string(),
+            Node::Action(action) => action.name().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    IsExplorer,
+    IsExtractor,
+    // Une ressource a été repérée et réservée
+    KnowsResource,
+    Carrying,
+    AtBase,
+}
+
+// Primitives des robots. Le ramassage et le dépôt sont faits par les règles du jeu
+// quand le robot arrive : `pick_up` et `deposit` l'y conduisent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Wait,
+    // Dévoile le brouillard autour du robot sans bouger
+    Scan,
+    RandomStep,
+    // Vers la frontière du brouillard la plus proche, échoue s'il n'y en a pas
+    Explore,
+    MoveToBase,
+    PickUp,
+    Deposit,
+}
+
+impl Action {
+    const ALL: [Action; 7] = [
+        Action::Wait,
+        Action::Scan,
+        Action::RandomStep,
+        Action::Explore,
+        Action::MoveToBase,
+        Action::PickUp,
+        Action::Deposit,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Wait => "wait",
+            Action::Scan => "scan",
+            Action::RandomStep => "random_step",
+            Action::Explore => "explore",
+            Action::MoveToBase => "move_to_base",
+            Action::PickUp => "pick_up",
+            Action::Deposit => "deposit",
+        }
+    }
+}
+
+// Chemin de la racine jusqu'à l'action qui occupe le robot : l'indice de l'enfant suivi à
+// chaque séquence, sélecteur ou décorateur
+pub type NodePath = Vec<usize>;
+
+// Résultat d'un noeud. Une action qui donne une commande occupe le robot pour ce tick
+// et arrête le parcours de l'arbre. Le chemin est construit à l'envers en remontant
+enum Status {
+    Success,
+    Failure,
+    Running(Command, NodePath),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BehaviourTree {
+    pub root: Node,
+}
+
+impl BehaviourTree {
+    pub fn load(path: &Path) -> Result<BehaviourTree, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        serde_json::from_str(&content).map_err(|err| {
+            ConfigError::Invalid(format!(
+                "arbre de comportement {} invalide: {}",
+                path.display(),
+                err
+            ))
+        })
+    }
+
+    // Parcourt l'arbre depuis la racine. Renvoie la commande du robot et le chemin de l'action
+    // qui l'a donnée ; si l'arbre se termine sans commande, le robot reste sur place
+    pub fn tick(
+        &self,
+        robot: &Robot,
+        world: &WorldView,
+        rng: &mut dyn RngCore,
+    ) -> (Command, Option<NodePath>) {
+        match evaluate(&self.root, robot, world, rng) {
+            Status::Running(command, mut path) => {
+                path.reverse();
+                (command, Some(path))
+            }
+            Status::Success | Status::Failure => (Command::Stay, None),
+        }
+    }
+
+    // Action au bout du chemin
+    pub fn action(&self, path: &[usize]) -> Option<Action> {
+        match path
+            .iter()
+            .try_fold(&self.root, |node, &child| node.children().get(child))?
+        {
+            Node::Action(action) => Some(*action),
+            _ => None,
+        }
+    }
+
+    // Noeuds traversés par le chemin, par exemple "selector[1] > sequence[1] > deposit"
+    pub fn describe(&self, path: &[usize]) -> Option<String> {
+        let mut node = &self.root;
+        let mut labels = Vec::with_capacity(path.len() + 1);
+        for &child in path {
+            labels.push(node.label(child));
+            node = node.children().get(child)?;
+        }
+        labels.push(node.label(0));
+        Some(labels.join(" > "))
+    }
+}
+
+fn evaluate(node: &Node, robot: &Robot, world: &WorldView, rng: &mut dyn RngCore) -> Status {
+    match node {
+        Node::Sequence(children) => {
+            for (index, child) in children.iter().enumerate() {
+                match evaluate(child, robot, world, rng) {
+                    Status::Success => {}
+                    status => return through(index, status),
+                }
+            }
+            Status::Success
+        }
+        Node::Selector(children) => {
+            for (index, child) in children.iter().enumerate() {
+                match evaluate(child, robot, world, rng) {
+                    Status::Failure => {}
+                    status => return through(index, status),
+                }
+            }
+            Status::Failure
+        }
+        Node::Invert(child) => match evaluate(child, robot, world, rng) {
+            Status::Success => Status::Failure,
+            Status::Failure => Status::Success,
+            running => through(0, running),
+        },
+        Node::ForceSuccess(child) => match evaluate(child, robot, world, rng) {
+            Status::Failure => Status::Success,
+            status => through(0, status),
+        },
+        Node::Condition(condition) => {
+            if check(*condition, robot, world) {
+                Status::Success
+            } else {
+                Status::Failure
+            }
+        }
+        Node::Action(action) => act(*action, robot, world, rng),
+    }
+}
+
+// Ajoute l'enfant `index` au chemin d'une action en cours
+fn through(index: usize, status: Status) -> Status {
+    match status {
+        Status::Running(command, mut path) => {
+            path.push(index);
+            Status::Running(command, path)
+        }
+        status => status,
+    }
+}
+
+fn check(condition: Condition, robot: &Robot, world: &WorldView) -> bool {
+    match condition {
+        Condition::IsExplorer => robot.role == Role::Explorer,
+        Condition::IsExtractor => robot.role == Role::Extractor,
+        Condition::KnowsResource => robot.resource_coords.is_some(),
+        Condition::Carrying => robot.carrying.is_some(),
+        Condition::AtBase => (robot.x, robot.y) == world.simulation.base_position,
+    }
+}
+
+fn act(action: Action, robot: &Robot, world: &WorldView, rng: &mut dyn RngCore) -> Status {
+    match command(action, robot, world, rng) {
+        Some(command) => Status::Running(command, NodePath::new()),
+        // Déjà à la base ou déjà chargé : le but de l'action est atteint
+        None if action == Action::MoveToBase
+            || (action == Action::PickUp && robot.carrying.is_some()) =>
+        {
+            Status::Success
+        }
+        None => Status::Failure,
+    }
+}
+
+// Commande d'une action, None si elle n'a rien à faire dans l'état du robot
+pub fn command(
+    action: Action,
+    robot: &Robot,
+    world: &WorldView,
+    rng: &mut dyn RngCore,
+) -> Option<Command> {
+    match action {
+        Action::Wait => Some(Command::Stay),
+        Action::Scan => Some(Command::Scan),
+        Action::RandomStep => Some(behaviour::random_step(robot, world, rng)),
+        Action::Explore => behaviour::frontier_target(robot, world).map(Command::GoToFrontier),
+        Action::MoveToBase => ((robot.x, robot.y) != world.simulation.base_position)
+            .then_some(Command::ReturnToBase { avoid_fog: false }),
+        Action::PickUp => match (robot.resource_coords, &robot.carrying) {
+            (Some(target), None) => Some(Command::GoTo {
+                target,
+                avoid_fog: true,
+            }),
+            _ => None,
+        },
+        Action::Deposit => robot
+            .carrying
+            .as_ref()
+            .map(|_| Command::ReturnToBase { avoid_fog: true }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RobotConfig, SimulationConfig};
+    use crate::simulation::{Cell, Simulation, SCAN_RADIUS};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+    use std::collections::BTreeMap;
+
+    fn simulation(strategy: &str, trees: &[(&str, BehaviourTree)]) -> Simulation {
+        let robot = RobotConfig {
+            strategy: strategy.to_string(),
+            ..RobotConfig::default()
+        };
+        let mut simulation = Simulation::new(&SimulationConfig {
+            robots: vec![robot; 3],
+            behaviour_trees: trees
+                .iter()
+                .map(|(name, tree)| (name.to_string(), tree.clone()))
+                .collect::<BTreeMap<_, _>>(),
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    fn tree(json: &str) -> BehaviourTree {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn tick(
+        tree: &BehaviourTree,
+        simulation: &Simulation,
+        robot: &Robot,
+    ) -> (Command, Option<Action>) {
+        let world = WorldView {
+            simulation,
+            known: &simulation.discovered,
+            claimed_frontiers: &[],
+        };
+        let (command, path) = tree.tick(robot, &world, &mut ChaCha12Rng::seed_from_u64(0));
+        (command, path.map(|path| tree.action(&path).unwrap()))
+    }
+
+    #[test]
+    fn test_parse_tree() {
+        let parsed = tree(
+            r#"{"selector": [{"sequence": [{"condition": "carrying"}, {"action": "deposit"}]},
+                {"invert": {"condition": "at_base"}}, {"force_success": {"action": "wait"}}]}"#,
+        );
+        assert_eq!(
+            parsed.root,
+            Node::Selector(vec![
+                Node::Sequence(vec![
+                    Node::Condition(Condition::Carrying),
+                    Node::Action(Action::Deposit),
+                ]),
+                Node::Invert(Box::new(Node::Condition(Condition::AtBase))),
+                Node::ForceSuccess(Box::new(Node::Action(Action::Wait))),
+            ])
+        );
+        assert!(serde_json::from_str::<BehaviourTree>(r#"{"action": "voler"}"#).is_err());
+        assert!(BehaviourTree::load(Path::new("behaviours/default.json")).is_ok());
+    }
+
+    #[test]
+    fn test_composite_and_decorator_nodes() {
+        let simulation = simulation("default", &[]);
+        let robot = simulation.robots[0].clone();
+
+        // La séquence s'arrête sur la condition fausse, le sélecteur passe à la suite
+        let selector = tree(
+            r#"{"selector": [{"sequence": [{"condition": "is_extractor"}, {"action": "wait"}]},
+                {"action": "scan"}]}"#,
+        );
+        assert_eq!(
+            tick(&selector, &simulation, &robot),
+            (Command::Scan, Some(Action::Scan))
+        );
+        let inverted = tree(
+            r#"{"sequence": [{"invert": {"condition": "is_extractor"}}, {"action": "wait"}]}"#,
+        );
+        assert_eq!(
+            tick(&inverted, &simulation, &robot),
+            (Command::Stay, Some(Action::Wait))
+        );
+        // Sans action qui donne une commande, le robot reste sur place
+        let forced = tree(r#"{"force_success": {"action": "deposit"}}"#);
+        assert_eq!(tick(&forced, &simulation, &robot), (Command::Stay, None));
+    }
+
+    #[test]
+    fn test_path_shows_branches_taken() {
+        let simulation = simulation("default", &[]);
+        let robot = simulation.robots[0].clone();
+        let tree = tree(
+            r#"{"selector": [{"sequence": [{"condition": "is_extractor"}, {"action": "wait"}]},
+                {"sequence": [{"condition": "is_explorer"},
+                    {"invert": {"condition": "carrying"}}, {"force_success": {"action": "scan"}}]}]}"#,
+        );
+        let world = WorldView {
+            simulation: &simulation,
+            known: &simulation.discovered,
+            claimed_frontiers: &[],
+        };
+        let (_, path) = tree.tick(&robot, &world, &mut ChaCha12Rng::seed_from_u64(0));
+        let path = path.unwrap();
+        assert_eq!(path, vec![1, 2, 0]);
+        assert_eq!(
+            tree.describe(&path).unwrap(),
+            "selector[1] > sequence[2] > force_success > scan"
+        );
+        assert_eq!(tree.describe(&[3]), None);
+    }
+
+    #[test]
+    fn test_actions_follow_robot_state() {
+        let simulation = simulation("default", &[]);
+        let mut robot = simulation.robots[0].clone();
+        let pick_up = tree(r#"{"action": "pick_up"}"#);
+        let deposit = tree(r#"{"action": "deposit"}"#);
+        let move_to_base = tree(r#"{"action": "move_to_base"}"#);
+
+        assert_eq!(tick(&pick_up, &simulation, &robot), (Command::Stay, None));
+        assert_eq!(
+            tick(&move_to_base, &simulation, &robot),
+            (Command::Stay, None)
+        );
+        robot.resource_coords = Some((3, 4));
+        assert_eq!(
+            tick(&pick_up, &simulation, &robot).0,
+            Command::GoTo {
+                target: (3, 4),
+                avoid_fog: true
+            }
+        );
+        assert_eq!(tick(&deposit, &simulation, &robot), (Command::Stay, None));
+        robot.carrying = Some(Cell::Crystal);
+        robot.x = (robot.x + 5) % simulation.map_width;
+        assert_eq!(
+            tick(&deposit, &simulation, &robot),
+            (
+                Command::ReturnToBase { avoid_fog: true },
+                Some(Action::Deposit)
+            )
+        );
+        assert_eq!(
+            tick(&move_to_base, &simulation, &robot).0,
+            Command::ReturnToBase { avoid_fog: false }
+        );
+    }
+
+    #[test]
+    fn test_default_tree_matches_default_behaviour() {
+        let default_tree = BehaviourTree::load(Path::new("behaviours/default.json")).unwrap();
+        let mut builtin = simulation("default", &[]);
+        let mut scripted = simulation("arbre", &[("arbre", default_tree)]);
+        for _ in 0..3000 {
+            builtin.step();
+            scripted.step();
+            let positions = |simulation: &Simulation| {
+                simulation
+                    .robots
+                    .iter()
+                    .map(|robot| (robot.x, robot.y))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(positions(&builtin), positions(&scripted));
+            assert_eq!(
+                (builtin.crystal_score, builtin.energy_score),
+                (scripted.crystal_score, scripted.energy_score)
+            );
+        }
+        assert!(scripted
+            .robots
+            .iter()
+            .all(|robot| robot.active_node.is_some()));
+        assert!(builtin
+            .robots
+            .iter()
+            .all(|robot| robot.active_node.is_none()));
+    }
+
+    #[test]
+    fn test_scan_reveals_radius() {
+        let scan = tree(r#"{"action": "scan"}"#);
+        let mut simulation = simulation("scan", &[("scan", scan)]);
+        // Les robots de vitesse 1 attendent un tick avant d'agir
+        simulation.step();
+        simulation.step();
+        let (x, y) = simulation.base_position;
+        let radius = SCAN_RADIUS;
+        assert!(simulation.discovered[y + radius][x + radius]);
+        assert!(!simulation.discovered[y + radius + 1][x]);
+        assert_eq!(simulation.robots[0].active_node, Some(vec![]));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::render;
+use crate::simulation::Simulation;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, ImageFormat, ImageResult};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CAPTURE_INTERVAL: u64 = 10;
+// Durée d'affichage d'une image du GIF
+const GIF_FRAME_DELAY_MS: u32 = 100;
+
+enum Output {
+    Gif(GifEncoder<BufWriter<File>>),
+    // Dossier de PNG numérotés
+    Frames(PathBuf),
+}
+
+// Capture d'une partie sans fenêtre, une image tous les `interval` ticks
+pub struct Capture {
+    output: Output,
+    interval: u64,
+    pixels_per_cell: u32,
+    frame_count: usize,
+}
+
+impl Capture {
+    // Un chemin en .gif donne un GIF animé, sinon un dossier de frame_00000.png...
+    pub fn new(path: &Path, interval: u64, pixels_per_cell: u32) -> ImageResult<Capture> {
+        let is_gif = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+        let output = if is_gif {
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            Output::Gif(encoder)
+        } else {
+            fs::create_dir_all(path)?;
+            Output::Frames(path.to_path_buf())
+        };
+        Ok(Capture {
+            output,
+            interval,
+            pixels_per_cell,
+            frame_count: 0,
+        })
+    }
+
+    // À appeler avant le premier tick puis après chaque tick
+    pub fn record(&mut self, simulation: &Simulation) -> ImageResult<()> {
+        if simulation.tick.is_multiple_of(self.interval) || simulation.game_over {
+            self.capture(simulation)?;
+        }
+        Ok(())
+    }
+
+    fn capture(&mut self, simulation: &Simulation) -> ImageResult<()> {
+        let image = render::render_frame(simulation, self.pixels_per_cell);
+        match &mut self.output {
+            Output::Gif(encoder) => encoder.encode_frame(Frame::from_parts(
+                DynamicImage::ImageRgb8(image).into_rgba8(),
+                0,
+                0,
+                Delay::from_numer_denom_ms(GIF_FRAME_DELAY_MS, 1),
+            ))?,
+            Output::Frames(dir) => image.save_with_format(
+                dir.join(format!("frame_{:05}.png", self.frame_count)),
+                ImageFormat::Png,
+            )?,
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // Termine le fichier et renvoie le nombre d'images capturées
+    pub fn finish(self) -> usize {
+        self.frame_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{EndConditions, SimulationConfig};
+
+    fn captured_run(path: &Path) -> usize {
+        let config = SimulationConfig {
+            map_width: 10,
+            map_height: 8,
+            end: EndConditions {
+                max_ticks: Some(25),
+                ..EndConditions::default()
+            },
+            ..SimulationConfig::default()
+        };
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        let mut capture = Capture::new(path, 10, 2).unwrap();
+        capture.record(&simulation).unwrap();
+        while !simulation.game_over {
+            simulation.step();
+            capture.record(&simulation).unwrap();
+        }
+        capture.finish()
+    }
+
+    #[test]
+    fn test_capture_png_sequence() {
+        let dir = std::env::temp_dir().join(format!("rust_game_frames_{}", std::process::id()));
+        // Ticks 0, 10, 20 et la fin de partie
+        assert_eq!(captured_run(&dir), 4);
+        let mut frames: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        frames.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[3], "frame_00003.png");
+    }
+
+    #[test]
+    fn test_capture_gif() {
+        use image::AnimationDecoder;
+
+        let path = std::env::temp_dir().join(format!("rust_game_{}.gif", std::process::id()));
+        assert_eq!(captured_run(&path), 4);
+        let decoder = image::codecs::gif::GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(frames.len(), 4);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::simulation::Simulation;
+
+type Position = (usize, usize);
+
+// Deux positions peuvent se parler par radio. Sans portée configurée, tout le monde
+// s'entend et partage la carte de la base
+pub fn in_range(simulation: &Simulation, a: Position, b: Position) -> bool {
+    simulation.communication_range.is_none_or(|range| {
+        let (dx, dy) =
+            simulation
+                .topology
+                .offsets(a, b, simulation.map_width, simulation.map_height);
+        dx * dx + dy * dy <= range * range
+    })
+}
+
+// Groupes reliés par radio, directement ou en se relayant. Un membre est un indice de
+// robot, ou None pour la base
+pub fn groups(simulation: &Simulation) -> Vec<Vec<Option<usize>>> {
+    let nodes: Vec<(Option<usize>, Position)> = std::iter::once((None, simulation.base_position))
+        .chain(
+            simulation
+                .robots
+                .iter()
+                .enumerate()
+                .map(|(index, robot)| (Some(index), (robot.x, robot.y))),
+        )
+        .collect();
+    let mut visited = vec![false; nodes.len()];
+    let mut groups = Vec::new();
+    for start in 0..nodes.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut group = vec![start];
+        let mut next = 0;
+        while let Some(&current) = group.get(next) {
+            next += 1;
+            for other in 0..nodes.len() {
+                if !visited[other] && in_range(simulation, nodes[current].1, nodes[other].1) {
+                    visited[other] = true;
+                    group.push(other);
+                }
+            }
+        }
+        group.sort_unstable();
+        groups.push(group.into_iter().map(|node| nodes[node].0).collect());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+    use crate::simulation::Topology;
+
+    fn simulation(range: Option<usize>, positions: &[Position]) -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            communication_range: range,
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation.base_position = (10, 10);
+        simulation.robots.truncate(positions.len());
+        for (robot, &(x, y)) in simulation.robots.iter_mut().zip(positions) {
+            robot.x = x;
+            robot.y = y;
+        }
+        simulation
+    }
+
+    #[test]
+    fn test_range_is_euclidean() {
+        let torus = simulation(Some(5), &[]);
+        assert!(in_range(&torus, (10, 10), (13, 14)));
+        assert!(!in_range(&torus, (10, 10), (14, 14)));
+        // Par le bord du tore
+        assert!(in_range(&torus, (0, 0), (38, 28)));
+        let mut bounded = simulation(Some(5), &[]);
+        bounded.topology = Topology::Bounded;
+        assert!(!in_range(&bounded, (0, 0), (38, 28)));
+        let unlimited = simulation(None, &[]);
+        assert!(in_range(&unlimited, (0, 0), (20, 15)));
+    }
+
+    #[test]
+    fn test_robots_relay_to_base() {
+        // Le deuxième robot n'entend la base que par le premier, le troisième est isolé
+        let mut simulation = simulation(Some(4), &[(13, 10), (16, 10), (30, 20)]);
+        assert_eq!(
+            groups(&simulation),
+            vec![vec![None, Some(0), Some(1)], vec![Some(2)]]
+        );
+        simulation.robots[0].x = 20;
+        assert_eq!(
+            groups(&simulation),
+            vec![vec![None], vec![Some(0), Some(1)], vec![Some(2)]]
+        );
+    }
+}
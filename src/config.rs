@@ -0,0 +1,574 @@
+use crate::ascii_map;
+use crate::battery::Battery;
+use crate::behaviour::{self, Strategies};
+use crate::behaviour_tree::BehaviourTree;
+use crate::script::RobotScript;
+use crate::sensor::{self, Sensor};
+use crate::simulation::{Exploration, Movement, Robot, Role, Topology};
+use crate::wasm_brain::WasmBrain;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "lecture du scénario impossible: {}", err),
+            ConfigError::Parse(err) => write!(f, "scénario TOML invalide: {}", err),
+            ConfigError::Invalid(message) => write!(f, "configuration invalide: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// Un robot de l'équipe de départ
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RobotConfig {
+    pub role: Role,
+    // Nombre de ticks d'attente entre deux déplacements
+    #[serde(default = "Robot::default_speed")]
+    pub speed: usize,
+    // Stratégie qui décide de ses actions (voir behaviour::strategy)
+    #[serde(default = "Robot::default_strategy")]
+    pub strategy: String,
+    // Type de capteur, défini dans la section [sensors] du scénario
+    #[serde(default = "Robot::default_sensor")]
+    pub sensor: String,
+}
+
+impl Default for RobotConfig {
+    fn default() -> Self {
+        RobotConfig {
+            role: Role::Explorer,
+            speed: Robot::default_speed(),
+            strategy: Robot::default_strategy(),
+            sensor: Robot::default_sensor(),
+        }
+    }
+}
+
+// Conditions d'arrêt en plus de la collecte de toutes les ressources
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EndConditions {
+    pub max_ticks: Option<u64>,
+    pub crystal_score: Option<u32>,
+    pub energy_score: Option<u32>,
+}
+
+// Paramètres de création d'une simulation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    pub seed: u64,
+    pub map_width: usize,
+    pub map_height: usize,
+    #[serde(default)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub movement: Movement,
+    pub noise_scale: f64,
+    pub noise_threshold: f64,
+    // Échelle du bruit qui génère le terrain (routes, sable, roche, boue)
+    pub terrain_scale: f64,
+    pub base_clear_radius: usize,
+    pub energy_count: usize,
+    pub crystal_count: usize,
+    pub robots: Vec<RobotConfig>,
+    #[serde(default)]
+    pub exploration: Exploration,
+    // Portée radio des robots en cases, sans limite si absente
+    #[serde(default)]
+    pub communication_range: Option<usize>,
+    // Modèle de batterie, les robots se déplacent sans limite si absent
+    #[serde(default)]
+    pub battery: Option<Battery>,
+    // Arbres de comportement chargés par le scénario, utilisables comme stratégies
+    #[serde(default)]
+    pub behaviour_trees: BTreeMap<String, BehaviourTree>,
+    // Scripts Rhai chargés par le scénario, utilisables comme stratégies
+    #[serde(default)]
+    pub scripts: BTreeMap<String, RobotScript>,
+    // Modules WASM chargés depuis le dossier du scénario, utilisables comme stratégies
+    #[serde(default)]
+    pub brains: BTreeMap<String, WasmBrain>,
+    // Capteurs des types de robots, par nom
+    #[serde(default)]
+    pub sensors: BTreeMap<String, Sensor>,
+    // Stratégies enregistrées par le code qui utilise la crate, absentes des fichiers
+    #[serde(skip)]
+    pub strategies: Strategies,
+    pub end: EndConditions,
+    // Carte ASCII dessinée à la main, qui remplace la génération par le bruit
+    #[serde(default)]
+    pub map_layout: Option<String>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            seed: 0,
+            map_width: 40,
+            map_height: 30,
+            topology: Topology::default(),
+            movement: Movement::default(),
+            noise_scale: 10.0,
+            noise_threshold: 0.5,
+            terrain_scale: 6.0,
+            base_clear_radius: 3,
+            energy_count: 5,
+            crystal_count: 10,
+            robots: vec![RobotConfig::default(); 3],
+            exploration: Exploration::default(),
+            communication_range: None,
+            battery: None,
+            behaviour_trees: BTreeMap::new(),
+            scripts: BTreeMap::new(),
+            brains: BTreeMap::new(),
+            sensors: BTreeMap::new(),
+            strategies: Strategies::default(),
+            end: EndConditions::default(),
+            map_layout: None,
+        }
+    }
+}
+
+// Format du fichier de scénario, chaque valeur absente garde sa valeur par défaut
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    seed: Option<u64>,
+    #[serde(default)]
+    map: MapSection,
+    #[serde(default)]
+    resources: ResourcesSection,
+    robots: Option<Vec<RobotConfig>>,
+    exploration: Option<Exploration>,
+    communication_range: Option<usize>,
+    battery: Option<Battery>,
+    // Nom de stratégie -> fichier JSON, relatif au fichier de scénario
+    #[serde(default)]
+    behaviour_trees: BTreeMap<String, PathBuf>,
+    // Nom de stratégie -> script Rhai, relatif au fichier de scénario
+    #[serde(default)]
+    scripts: BTreeMap<String, PathBuf>,
+    // Dossier de modules .wasm et .wat, chacun nommé d'après son fichier
+    brains: Option<PathBuf>,
+    // Nom de type de robot -> capteur
+    #[serde(default)]
+    sensors: BTreeMap<String, Sensor>,
+    #[serde(default)]
+    end: EndSection,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MapSection {
+    // Chemin d'une carte ASCII, relatif au fichier de scénario
+    file: Option<PathBuf>,
+    width: Option<usize>,
+    height: Option<usize>,
+    topology: Option<Topology>,
+    movement: Option<Movement>,
+    noise_scale: Option<f64>,
+    noise_threshold: Option<f64>,
+    terrain_scale: Option<f64>,
+    base_clear_radius: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResourcesSection {
+    energy: Option<usize>,
+    crystal: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EndSection {
+    max_ticks: Option<u64>,
+    crystal_score: Option<u32>,
+    energy_score: Option<u32>,
+}
+
+impl SimulationConfig {
+    pub fn load(path: &Path) -> Result<SimulationConfig, ConfigError> {
+        SimulationConfig::load_with_strategies(path, Strategies::default())
+    }
+
+    // Le scénario peut alors donner à ses robots les stratégies enregistrées
+    pub fn load_with_strategies(
+        path: &Path,
+        strategies: Strategies,
+    ) -> Result<SimulationConfig, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        SimulationConfig::parse_scenario(&content, base_dir, strategies)
+    }
+
+    #[cfg(test)]
+    pub fn from_toml_str(content: &str) -> Result<SimulationConfig, ConfigError> {
+        SimulationConfig::parse_scenario(content, Path::new(""), Strategies::default())
+    }
+
+    fn parse_scenario(
+        content: &str,
+        base_dir: &Path,
+        strategies: Strategies,
+    ) -> Result<SimulationConfig, ConfigError> {
+        let file: ScenarioFile = toml::from_str(content).map_err(ConfigError::Parse)?;
+        let default = SimulationConfig::default();
+        let mut config = SimulationConfig {
+            seed: file.seed.unwrap_or(default.seed),
+            map_width: file.map.width.unwrap_or(default.map_width),
+            map_height: file.map.height.unwrap_or(default.map_height),
+            topology: file.map.topology.unwrap_or(default.topology),
+            movement: file.map.movement.unwrap_or(default.movement),
+            noise_scale: file.map.noise_scale.unwrap_or(default.noise_scale),
+            noise_threshold: file.map.noise_threshold.unwrap_or(default.noise_threshold),
+            terrain_scale: file.map.terrain_scale.unwrap_or(default.terrain_scale),
+            base_clear_radius: file
+                .map
+                .base_clear_radius
+                .unwrap_or(default.base_clear_radius),
+            energy_count: file.resources.energy.unwrap_or(default.energy_count),
+            crystal_count: file.resources.crystal.unwrap_or(default.crystal_count),
+            robots: file.robots.unwrap_or(default.robots),
+            exploration: file.exploration.unwrap_or(default.exploration),
+            communication_range: file.communication_range,
+            battery: file.battery,
+            behaviour_trees: file
+                .behaviour_trees
+                .iter()
+                .map(|(name, path)| Ok((name.clone(), BehaviourTree::load(&base_dir.join(path))?)))
+                .collect::<Result<_, ConfigError>>()?,
+            scripts: file
+                .scripts
+                .iter()
+                .map(|(name, path)| Ok((name.clone(), RobotScript::load(&base_dir.join(path))?)))
+                .collect::<Result<_, ConfigError>>()?,
+            brains: match &file.brains {
+                Some(dir) => WasmBrain::load_dir(&base_dir.join(dir))?,
+                None => BTreeMap::new(),
+            },
+            sensors: file.sensors,
+            strategies,
+            end: EndConditions {
+                max_ticks: file.end.max_ticks,
+                crystal_score: file.end.crystal_score,
+                energy_score: file.end.energy_score,
+            },
+            map_layout: None,
+        };
+        if let Some(map_file) = &file.map.file {
+            config.load_map_layout(&base_dir.join(map_file))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load_map_layout(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let layout = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        self.set_map_layout(layout)
+    }
+
+    // Les dimensions de la carte suivent celles du dessin
+    pub fn set_map_layout(&mut self, layout: String) -> Result<(), ConfigError> {
+        let map = ascii_map::parse(&layout)?.map;
+        self.map_height = map.len();
+        self.map_width = map[0].len();
+        self.map_layout = Some(layout);
+        Ok(())
+    }
+
+    // Arbres, scripts et modules du scénario, et stratégies enregistrées, qui s'ajoutent
+    // aux stratégies intégrées
+    fn custom_strategies(&self) -> Vec<&str> {
+        self.behaviour_trees
+            .keys()
+            .chain(self.scripts.keys())
+            .chain(self.brains.keys())
+            .map(String::as_str)
+            .chain(self.strategies.names())
+            .collect()
+    }
+
+    // Première stratégie de robot ni intégrée, ni décrite par le scénario, ni enregistrée
+    pub fn unknown_strategy(&self) -> Option<&str> {
+        let custom = self.custom_strategies();
+        self.robots
+            .iter()
+            .map(|robot| robot.strategy.as_str())
+            .find(|name| behaviour::strategy(name).is_none() && !custom.contains(name))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.robots.is_empty() {
+            return Err(ConfigError::Invalid(
+                "il faut au moins un robot".to_string(),
+            ));
+        }
+        let custom = self.custom_strategies();
+        if let Some(name) = custom
+            .iter()
+            .find(|name| behaviour::strategy(name).is_some())
+        {
+            return Err(ConfigError::Invalid(format!(
+                "la stratégie '{}' du scénario porte le nom d'une stratégie existante",
+                name
+            )));
+        }
+        if let Some(name) = custom
+            .iter()
+            .enumerate()
+            .find(|&(index, name)| custom[..index].contains(name))
+            .map(|(_, name)| name)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "la stratégie '{}' est définie plusieurs fois",
+                name
+            )));
+        }
+        if let Some(strategy) = self.unknown_strategy() {
+            let mut names = behaviour::strategy_names();
+            names.extend(custom);
+            return Err(ConfigError::Invalid(format!(
+                "stratégie '{}' inconnue ({})",
+                strategy,
+                names.join(", ")
+            )));
+        }
+        if let Some(battery) = &self.battery {
+            battery.validate().map_err(ConfigError::Invalid)?;
+        }
+        for (name, sensor) in &self.sensors {
+            sensor.validate(name).map_err(ConfigError::Invalid)?;
+        }
+        if let Some(robot) = self.robots.iter().find(|robot| {
+            robot.sensor != sensor::DEFAULT_SENSOR && !self.sensors.contains_key(&robot.sensor)
+        }) {
+            return Err(ConfigError::Invalid(format!(
+                "capteur '{}' inconnu",
+                robot.sensor
+            )));
+        }
+        if self.end.max_ticks == Some(0) {
+            return Err(ConfigError::Invalid(
+                "max_ticks doit être strictement positif".to_string(),
+            ));
+        }
+
+        // Une carte dessinée porte déjà ses dimensions et ses ressources
+        if let Some(layout) = &self.map_layout {
+            ascii_map::parse(layout)?;
+            return Ok(());
+        }
+
+        if self.map_width < 3 || self.map_height < 3 {
+            return Err(ConfigError::Invalid(format!(
+                "la carte doit faire au moins 3x3 cases (reçu {}x{})",
+                self.map_width, self.map_height
+            )));
+        }
+        if !self.noise_scale.is_finite() || self.noise_scale <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "noise_scale doit être un nombre strictement positif (reçu {})",
+                self.noise_scale
+            )));
+        }
+        if !self.terrain_scale.is_finite() || self.terrain_scale <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "terrain_scale doit être un nombre strictement positif (reçu {})",
+                self.terrain_scale
+            )));
+        }
+        if !self.noise_threshold.is_finite() {
+            return Err(ConfigError::Invalid(format!(
+                "noise_threshold doit être un nombre fini (reçu {})",
+                self.noise_threshold
+            )));
+        }
+
+        // Cases hors bordure, moins celle de la base
+        let interior_cells = (self.map_width - 2) * (self.map_height - 2) - 1;
+        let resource_count = self.energy_count + self.crystal_count;
+        if resource_count > interior_cells {
+            return Err(ConfigError::Invalid(format!(
+                "{} ressources demandées mais la carte n'a que {} cases libres au maximum",
+                resource_count, interior_cells
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::SensorShape;
+
+    #[test]
+    fn test_scenario_overrides_defaults() {
+        let config = SimulationConfig::from_toml_str(
+            r#"
+            seed = 12
+            exploration = "random"
+            communication_range = 6
+
+            [map]
+            width = 50
+            topology = "bounded"
+            movement = "eight"
+            noise_threshold = 0.4
+
+            [resources]
+            crystal = 3
+
+            [[robots]]
+            role = "explorer"
+            sensor = "radar"
+
+            [[robots]]
+            role = "extractor"
+            speed = 2
+            strategy = "random"
+
+            [battery]
+            capacity = 200
+            reserve = 20
+
+            [sensors.radar]
+            radius = 3
+            shape = "circle"
+            line_of_sight = true
+
+            [end]
+            max_ticks = 500
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.seed, 12);
+        assert_eq!(config.map_width, 50);
+        assert_eq!(config.map_height, SimulationConfig::default().map_height);
+        assert_eq!(config.topology, Topology::Bounded);
+        assert_eq!(config.movement, Movement::Eight);
+        assert_eq!(config.noise_threshold, 0.4);
+        assert_eq!(config.crystal_count, 3);
+        assert_eq!(
+            config.energy_count,
+            SimulationConfig::default().energy_count
+        );
+        assert_eq!(
+            config.robots,
+            vec![
+                RobotConfig {
+                    role: Role::Explorer,
+                    speed: Robot::default_speed(),
+                    strategy: "default".to_string(),
+                    sensor: "radar".to_string(),
+                },
+                RobotConfig {
+                    role: Role::Extractor,
+                    speed: 2,
+                    strategy: "random".to_string(),
+                    sensor: "default".to_string(),
+                },
+            ]
+        );
+        assert_eq!(config.exploration, Exploration::Random);
+        assert_eq!(config.communication_range, Some(6));
+        assert_eq!(
+            config.battery,
+            Some(Battery {
+                capacity: 200,
+                reserve: 20,
+                ..Battery::default()
+            })
+        );
+        assert_eq!(
+            config.sensors["radar"],
+            Sensor {
+                radius: 3,
+                shape: SensorShape::Circle,
+                line_of_sight: true,
+                detection_probability: 1.0,
+            }
+        );
+        assert_eq!(config.end.max_ticks, Some(500));
+    }
+
+    #[test]
+    fn test_scenario_files_are_valid() {
+        for entry in std::fs::read_dir("scenarios").unwrap() {
+            let path = entry.unwrap().path();
+            if let Err(err) = SimulationConfig::load(&path) {
+                panic!("{}: {}", path.display(), err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scenario_map_file_sets_layout() {
+        let config = SimulationConfig::load(Path::new("scenarios/maze.toml")).unwrap();
+        let layout = config.map_layout.as_ref().unwrap();
+        let map = ascii_map::parse(layout).unwrap().map;
+        assert_eq!(
+            (config.map_width, config.map_height),
+            (map[0].len(), map.len())
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_is_rejected() {
+        let err = SimulationConfig::from_toml_str("[map]\nwidht = 50\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse(_)));
+        assert!(err.to_string().contains("widht"));
+    }
+
+    #[test]
+    fn test_too_many_resources_is_rejected() {
+        let err = SimulationConfig::from_toml_str(
+            "[map]\nwidth = 5\nheight = 5\n\n[resources]\nenergy = 5\ncrystal = 5\n",
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid(_)));
+        assert!(err.to_string().contains("10 ressources"));
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        for content in [
+            "[map]\nwidth = 2\n",
+            "[map]\nnoise_scale = 0.0\n",
+            "[map]\nterrain_scale = -1.0\n",
+            "robots = []\n",
+            "[[robots]]\nrole = \"explorer\"\nstrategy = \"telepathe\"\n",
+            "[behaviour_trees]\nrandom = \"behaviours/default.json\"\n",
+            "[scripts]\ndefault = \"scripts/explorer.rhai\"\n",
+            "brains = \"brains\"\n\n[behaviour_trees]\ncollector = \"behaviours/default.json\"\n",
+            "[behaviour_trees]\nbrain = \"behaviours/default.json\"\n\n[scripts]\nbrain = \"scripts/explorer.rhai\"\n",
+            "[[robots]]\nrole = \"explorer\"\nsensor = \"sonar\"\n",
+            "[sensors.default]\ndetection_probability = 1.5\n",
+            "[battery]\ncapacity = 10\nreserve = 10\n",
+            "[end]\nmax_ticks = 0\n",
+        ] {
+            assert!(
+                matches!(
+                    SimulationConfig::from_toml_str(content),
+                    Err(ConfigError::Invalid(_))
+                ),
+                "{}",
+                content
+            );
+        }
+    }
+}
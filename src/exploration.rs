@@ -0,0 +1,150 @@
+use crate::simulation::{cuts_corner, Cell, Movement, Simulation};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+type Position = (usize, usize);
+
+// Autour de la cible d'un autre explorateur, les cases de frontière lui sont laissées
+pub const CLAIM_RADIUS: usize = 3;
+
+// Case connue et franchissable dont une voisine est encore dans le brouillard :
+// s'y rendre dévoile du terrain
+pub fn is_frontier(simulation: &Simulation, known: &[Vec<bool>], (x, y): Position) -> bool {
+    known[y][x]
+        && simulation.map[y][x] != Cell::Obstacle
+        && (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter_map(|delta| {
+                simulation.topology.neighbour(
+                    (x, y),
+                    delta,
+                    simulation.map_width,
+                    simulation.map_height,
+                )
+            })
+            .any(|(nx, ny)| !known[ny][nx])
+}
+
+fn is_claimed(simulation: &Simulation, position: Position, claimed: &[Position]) -> bool {
+    claimed.iter().any(|&other| {
+        let (dx, dy) = simulation.topology.offsets(
+            position,
+            other,
+            simulation.map_width,
+            simulation.map_height,
+        );
+        dx <= CLAIM_RADIUS && dy <= CLAIM_RADIUS
+    })
+}
+
+// Frontière la moins coûteuse à atteindre par les cases connues, hors de celles
+// déjà visées par d'autres explorateurs
+pub fn nearest_frontier(
+    simulation: &Simulation,
+    known: &[Vec<bool>],
+    start: Position,
+    claimed: &[Position],
+) -> Option<Position> {
+    let mut costs = HashMap::new();
+    let mut open_set = BinaryHeap::new();
+    costs.insert(start, 0);
+    open_set.push(Reverse((0, start)));
+
+    while let Some(Reverse((cost, position))) = open_set.pop() {
+        if costs[&position] < cost {
+            continue;
+        }
+        if is_frontier(simulation, known, position) && !is_claimed(simulation, position, claimed) {
+            return Some(position);
+        }
+        for &delta in simulation.movement.directions() {
+            let Some((x, y)) = simulation.topology.neighbour(
+                position,
+                delta,
+                simulation.map_width,
+                simulation.map_height,
+            ) else {
+                continue;
+            };
+            if simulation.map[y][x] == Cell::Obstacle
+                || !known[y][x]
+                || cuts_corner(&simulation.map, simulation.topology, position, delta)
+            {
+                continue;
+            }
+            let next_cost = cost + simulation.terrain[y][x].cost() * Movement::step_cost(delta);
+            if costs.get(&(x, y)).is_none_or(|&known| next_cost < known) {
+                costs.insert((x, y), next_cost);
+                open_set.push(Reverse((next_cost, (x, y))));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+    use crate::simulation::Terrain;
+
+    fn open_simulation() -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig::default()).unwrap();
+        simulation.verbose = false;
+        simulation.map = vec![vec![Cell::Empty; 40]; 30];
+        simulation.terrain = vec![vec![Terrain::Plain; 40]; 30];
+        // Couloir découvert de (5, 10) à (20, 10)
+        for x in 5..=20 {
+            simulation.discovered[10][x] = true;
+        }
+        simulation
+    }
+
+    #[test]
+    fn test_frontier_cells() {
+        let mut simulation = open_simulation();
+        assert!(is_frontier(&simulation, &simulation.discovered, (12, 10)));
+        assert!(!is_frontier(&simulation, &simulation.discovered, (12, 11)));
+        for y in 9..=11 {
+            for x in 11..=13 {
+                simulation.discovered[y][x] = true;
+            }
+        }
+        assert!(!is_frontier(&simulation, &simulation.discovered, (12, 10)));
+        simulation.map[10][5] = Cell::Obstacle;
+        assert!(!is_frontier(&simulation, &simulation.discovered, (5, 10)));
+    }
+
+    #[test]
+    fn test_nearest_frontier_skips_claims() {
+        let mut simulation = open_simulation();
+        // Murs découverts de part et d'autre : seuls les deux bouts du couloir sont des frontières
+        for x in 5..=20 {
+            for y in [9, 11] {
+                simulation.discovered[y][x] = true;
+                simulation.map[y][x] = Cell::Obstacle;
+            }
+        }
+        assert_eq!(
+            nearest_frontier(&simulation, &simulation.discovered, (8, 10), &[]),
+            Some((5, 10))
+        );
+        assert_eq!(
+            nearest_frontier(&simulation, &simulation.discovered, (15, 10), &[]),
+            Some((20, 10))
+        );
+        assert_eq!(
+            nearest_frontier(&simulation, &simulation.discovered, (15, 10), &[(19, 12)]),
+            Some((5, 10))
+        );
+    }
+
+    #[test]
+    fn test_no_frontier_without_discovered_path() {
+        let simulation = open_simulation();
+        assert_eq!(
+            nearest_frontier(&simulation, &simulation.discovered, (30, 20), &[]),
+            None
+        );
+    }
+}
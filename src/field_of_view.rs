@@ -0,0 +1,185 @@
+// Champ de vision par shadowcasting symétrique (Albert Ford) : une case est vue si un
+// segment la relie au centre de la case d'origine sans traverser d'obstacle. Si A voit B,
+// B voit A. Les positions sont des écarts (dx, dy) par rapport à l'origine
+type Delta = (isize, isize);
+
+// Pente col / depth en fraction exacte, dénominateur positif
+#[derive(Clone, Copy)]
+struct Slope {
+    num: isize,
+    den: isize,
+}
+
+impl Slope {
+    // Pente du bord gauche d'une case
+    fn of(depth: isize, col: isize) -> Slope {
+        Slope {
+            num: 2 * col - 1,
+            den: 2 * depth,
+        }
+    }
+
+    // depth * pente, arrondi au plus proche avec les demis vers le haut
+    fn round_up(self, depth: isize) -> isize {
+        (2 * depth * self.num + self.den).div_euclid(2 * self.den)
+    }
+
+    // depth * pente, arrondi au plus proche avec les demis vers le bas
+    fn round_down(self, depth: isize) -> isize {
+        -(self.den - 2 * depth * self.num).div_euclid(2 * self.den)
+    }
+}
+
+// Une ligne d'un quadrant, entre deux pentes
+struct Row {
+    depth: isize,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    // Le centre de la case est entre les deux pentes : c'est ce qui rend la vue symétrique
+    fn is_symmetric(&self, col: isize) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+}
+
+// Les quatre quadrants (nord, sud, est, ouest), de (profondeur, colonne) vers (dx, dy)
+const QUADRANTS: [fn(isize, isize) -> Delta; 4] = [
+    |depth, col| (col, -depth),
+    |depth, col| (col, depth),
+    |depth, col| (depth, col),
+    |depth, col| (-depth, col),
+];
+
+// Cases vues jusqu'à `radius` cases de profondeur, dans une grille de (2 * radius + 1)²
+// indexée par [dy + radius][dx + radius]. Les obstacles sont vus mais cachent ce qui est
+// derrière eux
+pub fn shadowcast(radius: usize, blocks: impl Fn(Delta) -> bool) -> Vec<Vec<bool>> {
+    let size = 2 * radius + 1;
+    let radius = radius as isize;
+    let mut visible = vec![vec![false; size]; size];
+    let mut mark = |(dx, dy): Delta| visible[(dy + radius) as usize][(dx + radius) as usize] = true;
+    mark((0, 0));
+
+    for transform in QUADRANTS {
+        let mut rows = vec![Row {
+            depth: 1,
+            start: Slope { num: -1, den: 1 },
+            end: Slope { num: 1, den: 1 },
+        }];
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+            let mut previous_wall = None;
+            for col in row.start.round_up(row.depth)..=row.end.round_down(row.depth) {
+                let delta = transform(row.depth, col);
+                let wall = blocks(delta);
+                if wall || row.is_symmetric(col) {
+                    mark(delta);
+                }
+                match (previous_wall, wall) {
+                    // Sortie d'un mur : la vue reprend à partir de cette case
+                    (Some(true), false) => row.start = Slope::of(row.depth, col),
+                    // Entrée dans un mur : la partie déjà parcourue continue à la ligne suivante
+                    (Some(false), true) => {
+                        let mut next = row.next();
+                        next.end = Slope::of(row.depth, col);
+                        rows.push(next);
+                    }
+                    _ => {}
+                }
+                previous_wall = Some(wall);
+            }
+            if previous_wall == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha12Rng;
+
+    fn seen(visible: &[Vec<bool>], radius: isize, (dx, dy): Delta) -> bool {
+        visible[(dy + radius) as usize][(dx + radius) as usize]
+    }
+
+    #[test]
+    fn test_open_field_sees_everything() {
+        let visible = shadowcast(3, |_| false);
+        assert!(visible.iter().flatten().all(|&v| v));
+    }
+
+    #[test]
+    fn test_wall_casts_shadow() {
+        let wall = |(dx, dy): Delta| dx == 1 && dy.abs() <= 1;
+        let visible = shadowcast(4, wall);
+        assert!(seen(&visible, 4, (1, 0)));
+        assert!(!seen(&visible, 4, (2, 0)));
+        assert!(!seen(&visible, 4, (4, 1)));
+        assert!(seen(&visible, 4, (-4, 0)));
+        assert!(seen(&visible, 4, (2, 4)));
+    }
+
+    #[test]
+    fn test_neighbours_always_seen() {
+        let visible = shadowcast(1, |_| true);
+        assert!(visible.iter().flatten().all(|&v| v));
+    }
+
+    #[test]
+    fn test_vision_is_symmetric() {
+        let mut rng = ChaCha12Rng::seed_from_u64(3);
+        let size = 15;
+        let radius = 6;
+        for _ in 0..20 {
+            let walls: Vec<Vec<bool>> = (0..size)
+                .map(|_| (0..size).map(|_| rng.gen_bool(0.25)).collect())
+                .collect();
+            let wall = |(x, y): Delta| {
+                !(0..size as isize).contains(&x)
+                    || !(0..size as isize).contains(&y)
+                    || walls[y as usize][x as usize]
+            };
+            let floors: Vec<Delta> = (0..size as isize)
+                .flat_map(|y| (0..size as isize).map(move |x| (x, y)))
+                .filter(|&position| !wall(position))
+                .collect();
+            let views: Vec<Vec<Vec<bool>>> = floors
+                .iter()
+                .map(|&(x, y)| shadowcast(radius, |(dx, dy)| wall((x + dx, y + dy))))
+                .collect();
+            let radius = radius as isize;
+            for (a, from_a) in floors.iter().zip(&views) {
+                for (b, from_b) in floors.iter().zip(&views) {
+                    let delta = (b.0 - a.0, b.1 - a.1);
+                    if delta.0.abs() > radius || delta.1.abs() > radius {
+                        continue;
+                    }
+                    assert_eq!(
+                        seen(from_a, radius, delta),
+                        seen(from_b, radius, (-delta.0, -delta.1)),
+                        "{:?} {:?}",
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+}
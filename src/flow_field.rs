@@ -0,0 +1,235 @@
+use crate::simulation::{cuts_corner, Cell, Movement, Simulation};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+type Position = (usize, usize);
+
+// Coût du trajet de chaque case connue jusqu'à la base, partagé par tous les robots qui y
+// retournent : ils descendent le gradient au lieu de chercher chacun leur chemin
+#[derive(Clone, Debug, Default)]
+pub struct FlowField {
+    distances: Vec<Vec<Option<usize>>>,
+}
+
+// La base sert toujours de départ, les autres cases doivent avoir été découvertes
+fn known(simulation: &Simulation, (x, y): Position) -> bool {
+    simulation.map[y][x] != Cell::Obstacle && simulation.discovered[y][x]
+}
+
+impl FlowField {
+    pub fn new(simulation: &Simulation) -> FlowField {
+        let (x, y) = simulation.base_position;
+        let mut field = FlowField {
+            distances: vec![vec![None; simulation.map_width]; simulation.map_height],
+        };
+        field.distances[y][x] = Some(0);
+        field.propagate(simulation, vec![(0, simulation.base_position)]);
+        field
+    }
+
+    // Vide tant qu'il n'a pas été calculé, par exemple après le chargement d'un snapshot
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    // Coût du trajet jusqu'à la base, None si la case n'y est pas reliée par des cases connues
+    pub fn distance(&self, (x, y): Position) -> Option<usize> {
+        self.distances[y][x]
+    }
+
+    pub fn max_distance(&self) -> Option<usize> {
+        self.distances.iter().flatten().flatten().copied().max()
+    }
+
+    // Une case découverte ne peut que raccourcir les trajets : on la relie à ses voisines
+    // déjà atteintes puis on propage la baisse
+    pub fn reveal(&mut self, simulation: &Simulation, position: Position) {
+        if !known(simulation, position) {
+            return;
+        }
+        let best = simulation
+            .movement
+            .directions()
+            .iter()
+            .filter_map(|&delta| {
+                let next = simulation.topology.neighbour(
+                    position,
+                    delta,
+                    simulation.map_width,
+                    simulation.map_height,
+                )?;
+                if cuts_corner(&simulation.map, simulation.topology, position, delta) {
+                    return None;
+                }
+                Some(self.distance(next)? + step_cost(simulation, next, delta))
+            })
+            .min();
+        if let Some(best) = best {
+            if self.distance(position).is_none_or(|known| best < known) {
+                self.distances[position.1][position.0] = Some(best);
+                self.propagate(simulation, vec![(best, position)]);
+            }
+        }
+    }
+
+    // Dijkstra à l'envers : une case voisine atteint la base en passant par `position`
+    fn propagate(&mut self, simulation: &Simulation, start: Vec<(usize, Position)>) {
+        let mut open_set: BinaryHeap<_> = start.into_iter().map(Reverse).collect();
+        while let Some(Reverse((cost, position))) = open_set.pop() {
+            if self.distance(position).is_some_and(|known| known < cost) {
+                continue;
+            }
+            for &(dx, dy) in simulation.movement.directions() {
+                let Some(previous) = simulation.topology.neighbour(
+                    position,
+                    (dx, dy),
+                    simulation.map_width,
+                    simulation.map_height,
+                ) else {
+                    continue;
+                };
+                let delta = (-dx, -dy);
+                if !known(simulation, previous)
+                    || cuts_corner(&simulation.map, simulation.topology, previous, delta)
+                {
+                    continue;
+                }
+                let previous_cost = cost + step_cost(simulation, position, delta);
+                if self
+                    .distance(previous)
+                    .is_none_or(|known| previous_cost < known)
+                {
+                    self.distances[previous.1][previous.0] = Some(previous_cost);
+                    open_set.push(Reverse((previous_cost, previous)));
+                }
+            }
+        }
+    }
+
+    // Case voisine qui rapproche le plus de la base, None sur la base ou hors du champ
+    pub fn next_step(&self, simulation: &Simulation, position: Position) -> Option<Position> {
+        let current = self.distance(position)?;
+        simulation
+            .movement
+            .directions()
+            .iter()
+            .filter_map(|&delta| {
+                let next = simulation.topology.neighbour(
+                    position,
+                    delta,
+                    simulation.map_width,
+                    simulation.map_height,
+                )?;
+                if cuts_corner(&simulation.map, simulation.topology, position, delta) {
+                    return None;
+                }
+                Some((
+                    self.distance(next)? + step_cost(simulation, next, delta),
+                    next,
+                ))
+            })
+            .min()
+            .filter(|&(cost, _)| cost == current)
+            .map(|(_, next)| next)
+    }
+}
+
+fn step_cost(simulation: &Simulation, (x, y): Position, delta: (isize, isize)) -> usize {
+    simulation.terrain[y][x].cost() * Movement::step_cost(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+    use crate::simulation::{Terrain, Topology};
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    fn simulation(movement: Movement) -> Simulation {
+        let config = SimulationConfig {
+            seed: 7,
+            movement,
+            ..SimulationConfig::default()
+        };
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    #[test]
+    fn test_distances_match_a_star() {
+        for movement in [Movement::Four, Movement::Eight] {
+            let mut simulation = simulation(movement);
+            simulation.discovered = vec![vec![true; 40]; 30];
+            let field = FlowField::new(&simulation);
+            for y in (0..30).step_by(3) {
+                for x in (0..40).step_by(3) {
+                    let path = simulation.a_star_pathfinding(
+                        (x, y),
+                        simulation.base_position,
+                        Some(&simulation.discovered),
+                    );
+                    let cost = path.map(|path| {
+                        path.windows(2)
+                            .map(|pair| {
+                                let (dx, dy) =
+                                    simulation.topology.offsets(pair[0], pair[1], 40, 30);
+                                step_cost(&simulation, pair[1], (dx as isize, dy as isize))
+                            })
+                            .sum::<usize>()
+                    });
+                    let cost = cost.filter(|_| simulation.map[y][x] != Cell::Obstacle);
+                    assert_eq!(field.distance((x, y)), cost, "{:?}", (x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reveal_matches_full_computation() {
+        let mut simulation = simulation(Movement::Eight);
+        let mut field = FlowField::new(&simulation);
+        let mut cells: Vec<Position> = (0..30).flat_map(|y| (0..40).map(move |x| (x, y))).collect();
+        cells.shuffle(&mut ChaCha12Rng::seed_from_u64(3));
+        for (count, &position) in cells.iter().enumerate() {
+            simulation.discovered[position.1][position.0] = true;
+            field.reveal(&simulation, position);
+            if count % 100 == 0 {
+                assert_eq!(field.distances, FlowField::new(&simulation).distances);
+            }
+        }
+        assert_eq!(field.distances, FlowField::new(&simulation).distances);
+    }
+
+    #[test]
+    fn test_fog_limits_field() {
+        let simulation = simulation(Movement::Four);
+        let field = FlowField::new(&simulation);
+        assert_eq!(field.distance(simulation.base_position), Some(0));
+        assert_eq!(field.max_distance(), Some(0));
+    }
+
+    #[test]
+    fn test_next_step_leads_to_base() {
+        let mut simulation = simulation(Movement::Four);
+        simulation.topology = Topology::Torus;
+        simulation.map = vec![vec![Cell::Empty; 40]; 30];
+        simulation.terrain = vec![vec![Terrain::Plain; 40]; 30];
+        simulation.discovered = vec![vec![true; 40]; 30];
+        simulation.base_position = (1, 1);
+        let field = FlowField::new(&simulation);
+
+        // Par le bord du tore : 2 cases à gauche et 2 en haut
+        let mut position = (39, 29);
+        assert_eq!(field.distance(position), Some(4 * 20));
+        let mut steps = 0;
+        while let Some(next) = field.next_step(&simulation, position) {
+            assert!(field.distance(next) < field.distance(position));
+            position = next;
+            steps += 1;
+        }
+        assert_eq!((position, steps), ((1, 1), 4));
+    }
+}
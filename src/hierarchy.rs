@@ -0,0 +1,601 @@
+use crate::simulation::{cuts_corner, Cell, Movement, Simulation, Topology};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Côté d'un cluster, en cases
+pub const CLUSTER_SIZE: usize = 16;
+// En dessous, A* sur toute la carte reste plus rapide que de construire la hiérarchie
+pub const MIN_CELLS: usize = 256 * 256;
+// Une ouverture plus large que ça entre deux clusters reçoit un portail à chaque bout
+const WIDE_ENTRANCE: usize = 6;
+
+type Position = (usize, usize);
+
+// Pathfinding hiérarchique façon HPA* : la carte est découpée en clusters reliés par des
+// portails, A* cherche d'abord un chemin de portail en portail puis le détaille cluster par cluster
+#[derive(Clone, Debug, Default)]
+pub struct Hierarchy {
+    built: bool,
+    clusters_x: usize,
+    clusters_y: usize,
+    // Pour chaque cluster, les arêtes qui partent de ses portails : vers les autres portails
+    // du cluster et vers la case voisine de l'autre côté de la bordure
+    edges: Vec<HashMap<Position, Vec<(Position, usize)>>>,
+    dirty: Vec<bool>,
+}
+
+// Une hiérarchie sur toute la carte, une sur la carte de la base pour les robots qui évitent
+// le brouillard et, avec une portée radio, une par carte de robot. C'est un cache reconstruit
+// à la demande : il n'est pas sérialisé
+#[derive(Clone, Debug, Default)]
+pub struct Hierarchies {
+    pub open: Hierarchy,
+    pub discovered: Hierarchy,
+    robots: Vec<Hierarchy>,
+}
+
+impl Hierarchies {
+    // Hiérarchie de la carte du robot d'indice `robot`, créée vide à sa première recherche
+    pub fn robot_mut(&mut self, robot: usize) -> &mut Hierarchy {
+        if self.robots.len() <= robot {
+            self.robots.resize_with(robot + 1, Hierarchy::default);
+        }
+        &mut self.robots[robot]
+    }
+
+    // Une case rejoint la carte du robot `robot` ; sans recherche de sa part, il n'y a rien
+    // à invalider
+    pub fn mark_robot_changed(
+        &mut self,
+        robot: usize,
+        position: Position,
+        topology: Topology,
+        width: usize,
+        height: usize,
+    ) {
+        if let Some(hierarchy) = self.robots.get_mut(robot) {
+            hierarchy.mark_changed(position, topology, width, height);
+        }
+    }
+}
+
+// Avec une carte `known`, seules les cases qu'elle connaît sont franchissables
+fn passable(simulation: &Simulation, known: Option<&[Vec<bool>]>, (x, y): Position) -> bool {
+    simulation.map[y][x] != Cell::Obstacle && known.is_none_or(|known| known[y][x])
+}
+
+fn step_cost(simulation: &Simulation, (x, y): Position, delta: (isize, isize)) -> usize {
+    simulation.terrain[y][x].cost() * Movement::step_cost(delta)
+}
+
+impl Hierarchy {
+    fn cluster_of(&self, (x, y): Position) -> usize {
+        (y / CLUSTER_SIZE) * self.clusters_x + x / CLUSTER_SIZE
+    }
+
+    // Colonnes et lignes couvertes par un cluster, les derniers peuvent être plus petits
+    fn bounds(&self, simulation: &Simulation, cluster: usize) -> (Position, Position) {
+        let (cx, cy) = (cluster % self.clusters_x, cluster / self.clusters_x);
+        (
+            (cx * CLUSTER_SIZE, cy * CLUSTER_SIZE),
+            (
+                ((cx + 1) * CLUSTER_SIZE).min(simulation.map_width),
+                ((cy + 1) * CLUSTER_SIZE).min(simulation.map_height),
+            ),
+        )
+    }
+
+    // À appeler quand une case change de franchissabilité : son cluster est recalculé à la
+    // prochaine recherche, et le cluster voisin aussi si elle touche leur bordure commune
+    pub fn mark_changed(
+        &mut self,
+        position: Position,
+        topology: Topology,
+        width: usize,
+        height: usize,
+    ) {
+        if !self.built {
+            return;
+        }
+        let cluster = self.cluster_of(position);
+        self.dirty[cluster] = true;
+        for &delta in Movement::Four.directions() {
+            if let Some(other) = topology.neighbour(position, delta, width, height) {
+                let other_cluster = self.cluster_of(other);
+                self.dirty[other_cluster] = true;
+            }
+        }
+    }
+
+    fn refresh(&mut self, simulation: &Simulation, known: Option<&[Vec<bool>]>) {
+        if !self.built {
+            self.clusters_x = simulation.map_width.div_ceil(CLUSTER_SIZE);
+            self.clusters_y = simulation.map_height.div_ceil(CLUSTER_SIZE);
+            let count = self.clusters_x * self.clusters_y;
+            self.edges = vec![HashMap::new(); count];
+            self.dirty = vec![true; count];
+            self.built = true;
+        }
+        for cluster in 0..self.dirty.len() {
+            if self.dirty[cluster] {
+                self.edges[cluster] = self.build_cluster(simulation, known, cluster);
+                self.dirty[cluster] = false;
+            }
+        }
+    }
+
+    // Paires (case du cluster, case voisine) par où sortir du côté `delta`. Chaque ouverture
+    // continue donne un portail au milieu, ou deux aux bouts si elle est large. Le cluster
+    // voisin parcourt la même bordure dans le même ordre et choisit donc les mêmes cases
+    fn entrances(
+        &self,
+        simulation: &Simulation,
+        known: Option<&[Vec<bool>]>,
+        cluster: usize,
+        delta: (isize, isize),
+    ) -> Vec<(Position, Position)> {
+        let ((x0, y0), (x1, y1)) = self.bounds(simulation, cluster);
+        let border: Vec<Position> = match delta {
+            (1, 0) => (y0..y1).map(|y| (x1 - 1, y)).collect(),
+            (-1, 0) => (y0..y1).map(|y| (x0, y)).collect(),
+            (0, 1) => (x0..x1).map(|x| (x, y1 - 1)).collect(),
+            _ => (x0..x1).map(|x| (x, y0)).collect(),
+        };
+
+        let mut entrances = Vec::new();
+        let mut run: Vec<(Position, Position)> = Vec::new();
+        for position in border {
+            let crossing = simulation
+                .topology
+                .neighbour(position, delta, simulation.map_width, simulation.map_height)
+                .filter(|&other| {
+                    self.cluster_of(other) != cluster
+                        && passable(simulation, known, position)
+                        && passable(simulation, known, other)
+                });
+            match crossing {
+                Some(other) => run.push((position, other)),
+                None => flush_run(&mut run, &mut entrances),
+            }
+        }
+        flush_run(&mut run, &mut entrances);
+        entrances
+    }
+
+    fn build_cluster(
+        &self,
+        simulation: &Simulation,
+        known: Option<&[Vec<bool>]>,
+        cluster: usize,
+    ) -> HashMap<Position, Vec<(Position, usize)>> {
+        let mut edges: HashMap<Position, Vec<(Position, usize)>> = HashMap::new();
+        for &delta in Movement::Four.directions() {
+            for (portal, other) in self.entrances(simulation, known, cluster, delta) {
+                edges
+                    .entry(portal)
+                    .or_default()
+                    .push((other, step_cost(simulation, other, delta)));
+            }
+        }
+
+        let mut portals: Vec<Position> = edges.keys().copied().collect();
+        portals.sort();
+        for &portal in &portals {
+            let reached = self.local_search(simulation, known, cluster, portal, false);
+            for &other in &portals {
+                if other != portal {
+                    if let Some(&(cost, _)) = reached.get(&other) {
+                        edges.get_mut(&portal).unwrap().push((other, cost));
+                    }
+                }
+            }
+        }
+        edges
+    }
+
+    // Dijkstra limité au cluster. Renvoie pour chaque case atteinte son coût et la case
+    // précédente ; à l'envers, le coût pour aller jusqu'à `origin` et la case suivante
+    fn local_search(
+        &self,
+        simulation: &Simulation,
+        known: Option<&[Vec<bool>]>,
+        cluster: usize,
+        origin: Position,
+        reverse: bool,
+    ) -> HashMap<Position, (usize, Position)> {
+        let mut reached = HashMap::new();
+        let mut open_set = BinaryHeap::new();
+        reached.insert(origin, (0, origin));
+        open_set.push(Reverse((0, origin)));
+
+        while let Some(Reverse((cost, position))) = open_set.pop() {
+            if reached[&position].0 < cost {
+                continue;
+            }
+            for &(dx, dy) in simulation.movement.directions() {
+                let Some(next) = simulation.topology.neighbour(
+                    position,
+                    (dx, dy),
+                    simulation.map_width,
+                    simulation.map_height,
+                ) else {
+                    continue;
+                };
+                if self.cluster_of(next) != cluster || !passable(simulation, known, next) {
+                    continue;
+                }
+                // À l'envers, le déplacement réel va de `next` vers `position`
+                let (from, to, delta) = if reverse {
+                    (next, position, (-dx, -dy))
+                } else {
+                    (position, next, (dx, dy))
+                };
+                if cuts_corner(&simulation.map, simulation.topology, from, delta) {
+                    continue;
+                }
+                let next_cost = cost + step_cost(simulation, to, delta);
+                if reached.get(&next).is_none_or(|&(best, _)| next_cost < best) {
+                    reached.insert(next, (next_cost, position));
+                    open_set.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        reached
+    }
+
+    pub fn find_path(
+        &mut self,
+        simulation: &Simulation,
+        start: Position,
+        goal: Position,
+        known: Option<&[Vec<bool>]>,
+    ) -> Option<Vec<Position>> {
+        if start == goal {
+            return Some(vec![start]);
+        }
+        if !passable(simulation, known, goal) {
+            return None;
+        }
+        self.refresh(simulation, known);
+
+        let start_cluster = self.cluster_of(start);
+        let goal_cluster = self.cluster_of(goal);
+        let from_start = self.local_search(simulation, known, start_cluster, start, false);
+        let to_goal = self.local_search(simulation, known, goal_cluster, goal, true);
+
+        // A* sur le graphe des portails, avec le départ et l'arrivée comme noeuds temporaires
+        let neighbours = |position: Position| -> Vec<(Position, usize)> {
+            let mut neighbours = Vec::new();
+            if position == start {
+                neighbours.extend(
+                    self.edges[start_cluster]
+                        .keys()
+                        .filter_map(|&portal| Some((portal, from_start.get(&portal)?.0))),
+                );
+            }
+            if let Some(edges) = self.edges[self.cluster_of(position)].get(&position) {
+                neighbours.extend(edges.iter().copied());
+            }
+            if self.cluster_of(position) == goal_cluster {
+                if let Some(&(cost, _)) = to_goal.get(&position) {
+                    neighbours.push((goal, cost));
+                }
+            }
+            neighbours
+        };
+
+        let mut open_set = BinaryHeap::new();
+        let mut g_score = HashMap::new();
+        let mut came_from = HashMap::new();
+        g_score.insert(start, 0);
+        open_set.push(Reverse((simulation.heuristic(start, goal), 0, start)));
+
+        let mut abstract_path = None;
+        while let Some(Reverse((_, cost, position))) = open_set.pop() {
+            if position == goal {
+                let mut path = vec![goal];
+                while let Some(&previous) = came_from.get(path.last().unwrap()) {
+                    path.push(previous);
+                }
+                path.reverse();
+                abstract_path = Some(path);
+                break;
+            }
+            if g_score[&position] < cost {
+                continue;
+            }
+            for (next, edge_cost) in neighbours(position) {
+                let next_cost = cost + edge_cost;
+                if g_score.get(&next).is_none_or(|&best| next_cost < best) {
+                    g_score.insert(next, next_cost);
+                    came_from.insert(next, position);
+                    open_set.push(Reverse((
+                        next_cost + simulation.heuristic(next, goal),
+                        next_cost,
+                        next,
+                    )));
+                }
+            }
+        }
+
+        let abstract_path = abstract_path?;
+        Some(self.refine(simulation, known, &abstract_path, &from_start, &to_goal))
+    }
+
+    // Remplace chaque arête du chemin abstrait par les cases traversées
+    fn refine(
+        &self,
+        simulation: &Simulation,
+        known: Option<&[Vec<bool>]>,
+        abstract_path: &[Position],
+        from_start: &HashMap<Position, (usize, Position)>,
+        to_goal: &HashMap<Position, (usize, Position)>,
+    ) -> Vec<Position> {
+        let (start, goal) = (abstract_path[0], abstract_path[abstract_path.len() - 1]);
+        let mut path = vec![start];
+        for pair in abstract_path.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let cluster = self.cluster_of(from);
+            if to == goal && cluster == self.cluster_of(goal) {
+                // Les cases suivantes jusqu'à l'arrivée sont déjà connues
+                let mut position = from;
+                while position != goal {
+                    position = to_goal[&position].1;
+                    path.push(position);
+                }
+            } else if cluster != self.cluster_of(to) {
+                path.push(to);
+            } else {
+                let searched;
+                let reached = if from == start {
+                    from_start
+                } else {
+                    searched = self.local_search(simulation, known, cluster, from, false);
+                    &searched
+                };
+                let mut segment = vec![to];
+                while *segment.last().unwrap() != from {
+                    segment.push(reached[segment.last().unwrap()].1);
+                }
+                path.extend(segment.iter().rev().skip(1));
+            }
+        }
+        path
+    }
+}
+
+fn flush_run(run: &mut Vec<(Position, Position)>, entrances: &mut Vec<(Position, Position)>) {
+    if run.len() > WIDE_ENTRANCE {
+        entrances.push(run[0]);
+        entrances.push(run[run.len() - 1]);
+    } else if !run.is_empty() {
+        entrances.push(run[run.len() / 2]);
+    }
+    run.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+    use crate::simulation::{Terrain, STRAIGHT_STEP_COST};
+
+    fn open_simulation(width: usize, height: usize, topology: Topology) -> Simulation {
+        let config = SimulationConfig {
+            map_width: width,
+            map_height: height,
+            topology,
+            ..SimulationConfig::default()
+        };
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        simulation.map = vec![vec![Cell::Empty; width]; height];
+        simulation.terrain = vec![vec![Terrain::Plain; width]; height];
+        simulation.discovered = vec![vec![true; width]; height];
+        simulation
+    }
+
+    // Vérifie que le chemin avance case par case sans traverser d'obstacle et renvoie son coût
+    fn checked_cost(simulation: &Simulation, path: &[Position]) -> usize {
+        path.windows(2)
+            .map(|pair| {
+                let (dx, dy) = simulation.topology.offsets(
+                    pair[0],
+                    pair[1],
+                    simulation.map_width,
+                    simulation.map_height,
+                );
+                assert!(dx + dy > 0 && dx <= 1 && dy <= 1, "{:?}", pair);
+                assert!(passable(simulation, None, pair[1]), "{:?}", pair[1]);
+                step_cost(simulation, pair[1], (dx as isize, dy as isize))
+            })
+            .sum()
+    }
+
+    fn a_star_cost(simulation: &Simulation, start: Position, goal: Position) -> usize {
+        let path = simulation.a_star_pathfinding(start, goal, None).unwrap();
+        checked_cost(simulation, &path)
+    }
+
+    #[test]
+    fn test_path_on_open_map_is_near_optimal() {
+        let simulation = open_simulation(70, 50, Topology::Bounded);
+        let mut hierarchy = Hierarchy::default();
+        for (start, goal) in [((1, 1), (68, 48)), ((5, 40), (60, 3)), ((33, 0), (33, 49))] {
+            let path = hierarchy.find_path(&simulation, start, goal, None).unwrap();
+            assert_eq!((path[0], path[path.len() - 1]), (start, goal));
+            let cost = checked_cost(&simulation, &path);
+            assert!(cost * 10 <= a_star_cost(&simulation, start, goal) * 12);
+        }
+    }
+
+    #[test]
+    fn test_path_through_single_gap() {
+        let mut simulation = open_simulation(48, 48, Topology::Bounded);
+        for row in simulation.map.iter_mut() {
+            row[20] = Cell::Obstacle;
+        }
+        simulation.map[40][20] = Cell::Empty;
+        let mut hierarchy = Hierarchy::default();
+        let path = hierarchy
+            .find_path(&simulation, (2, 2), (45, 2), None)
+            .unwrap();
+        assert!(path.contains(&(20, 40)));
+        assert_eq!(
+            checked_cost(&simulation, &path),
+            a_star_cost(&simulation, (2, 2), (45, 2))
+        );
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        let mut simulation = open_simulation(48, 48, Topology::Bounded);
+        for row in simulation.map.iter_mut() {
+            row[20] = Cell::Obstacle;
+        }
+        let mut hierarchy = Hierarchy::default();
+        assert_eq!(
+            hierarchy.find_path(&simulation, (2, 2), (45, 2), None),
+            None
+        );
+        simulation.map[2][45] = Cell::Obstacle;
+        assert_eq!(
+            hierarchy.find_path(&simulation, (2, 2), (45, 2), None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_changed_cell_updates_clusters() {
+        let mut simulation = open_simulation(48, 48, Topology::Bounded);
+        for row in simulation.map.iter_mut() {
+            row[20] = Cell::Obstacle;
+        }
+        simulation.map[10][20] = Cell::Empty;
+        let mut hierarchy = Hierarchy::default();
+        assert!(hierarchy
+            .find_path(&simulation, (2, 2), (45, 2), None)
+            .is_some());
+
+        // Le seul passage se ferme puis un autre s'ouvre sur la bordure entre deux clusters
+        simulation.map[10][20] = Cell::Obstacle;
+        hierarchy.mark_changed((20, 10), simulation.topology, 48, 48);
+        assert_eq!(
+            hierarchy.find_path(&simulation, (2, 2), (45, 2), None),
+            None
+        );
+
+        simulation.map[32][20] = Cell::Empty;
+        hierarchy.mark_changed((20, 32), simulation.topology, 48, 48);
+        let path = hierarchy
+            .find_path(&simulation, (2, 2), (45, 2), None)
+            .unwrap();
+        assert!(path.contains(&(20, 32)));
+        checked_cost(&simulation, &path);
+    }
+
+    #[test]
+    fn test_path_wraps_on_torus() {
+        let simulation = open_simulation(64, 64, Topology::Torus);
+        let mut hierarchy = Hierarchy::default();
+        let path = hierarchy
+            .find_path(&simulation, (1, 30), (62, 30), None)
+            .unwrap();
+        // Le chemin passe par le bord au lieu de traverser toute la carte
+        assert!(path.iter().all(|&(x, _)| x <= 1 || x >= 62));
+        assert!(checked_cost(&simulation, &path) <= 5 * STRAIGHT_STEP_COST * Terrain::Plain.cost());
+    }
+
+    #[test]
+    fn test_path_avoids_fog() {
+        let mut simulation = open_simulation(48, 48, Topology::Bounded);
+        simulation.discovered = vec![vec![false; 48]; 48];
+        for x in 0..48 {
+            simulation.discovered[5][x] = true;
+            simulation.discovered[40][x] = true;
+        }
+        for y in 5..=40 {
+            simulation.discovered[y][44] = true;
+        }
+        let mut hierarchy = Hierarchy::default();
+        let path = hierarchy
+            .find_path(&simulation, (2, 5), (2, 40), Some(&simulation.discovered))
+            .unwrap();
+        assert!(path.iter().all(|&(x, y)| simulation.discovered[y][x]));
+        assert!(path.contains(&(44, 20)));
+        assert_eq!(
+            hierarchy.find_path(&simulation, (2, 5), (2, 20), Some(&simulation.discovered)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_path_inside_one_cluster() {
+        let simulation = open_simulation(48, 48, Topology::Bounded);
+        let mut hierarchy = Hierarchy::default();
+        let path = hierarchy
+            .find_path(&simulation, (17, 17), (20, 17), None)
+            .unwrap();
+        assert_eq!(path, vec![(17, 17), (18, 17), (19, 17), (20, 17)]);
+    }
+
+    // cargo test --release -- --ignored --nocapture bench_hierarchy
+    #[test]
+    #[ignore]
+    fn bench_hierarchy() {
+        let config = SimulationConfig {
+            seed: 3,
+            map_width: 1000,
+            map_height: 1000,
+            noise_scale: 40.0,
+            topology: Topology::Bounded,
+            ..SimulationConfig::default()
+        };
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        let mut hierarchy = Hierarchy::default();
+        let start = std::time::Instant::now();
+        hierarchy.refresh(&simulation, None);
+        let build = start.elapsed();
+
+        let queries: Vec<(Position, Position)> = (0..20)
+            .map(|i| {
+                (
+                    (i * 37 % 1000, i * 53 % 1000),
+                    (999 - i * 41 % 1000, 999 - i * 29 % 1000),
+                )
+            })
+            .filter(|&(a, b)| passable(&simulation, None, a) && passable(&simulation, None, b))
+            .collect();
+        let start = std::time::Instant::now();
+        let flat: Vec<_> = queries
+            .iter()
+            .map(|&(a, b)| simulation.a_star_pathfinding(a, b, None))
+            .collect();
+        let flat_time = start.elapsed();
+        let start = std::time::Instant::now();
+        let hierarchical: Vec<_> = queries
+            .iter()
+            .map(|&(a, b)| hierarchy.find_path(&simulation, a, b, None))
+            .collect();
+        let hierarchical_time = start.elapsed();
+
+        let (mut flat_cost, mut hierarchical_cost) = (0, 0);
+        for (flat, hierarchical) in flat.iter().zip(&hierarchical) {
+            assert_eq!(flat.is_some(), hierarchical.is_some());
+            if let (Some(flat), Some(hierarchical)) = (flat, hierarchical) {
+                flat_cost += checked_cost(&simulation, flat);
+                hierarchical_cost += checked_cost(&simulation, hierarchical);
+            }
+        }
+        println!(
+            "{} chemins, A*: {:?}, hiérarchique: {:?} (construction {:?}), x{:.1}, coût +{:.1}%",
+            queries.len(),
+            flat_time,
+            hierarchical_time,
+            build,
+            flat_time.as_secs_f64() / hierarchical_time.as_secs_f64(),
+            (hierarchical_cost as f64 / flat_cost as f64 - 1.0) * 100.0
+        );
+    }
+}
@@ -0,0 +1,23 @@
+// Simulation de robots explorateurs et extracteurs, sans fenêtre. Le binaire `rust-game` y
+// ajoute la fenêtre ggez et la ligne de commande. Une autre crate peut écrire ses propres
+// stratégies en implémentant behaviour::Behaviour (voir behaviour::Strategies)
+pub mod ascii_map;
+pub mod batch;
+pub mod battery;
+pub mod behaviour;
+pub mod behaviour_tree;
+pub mod capture;
+mod communication;
+pub mod config;
+mod exploration;
+mod field_of_view;
+pub mod flow_field;
+mod hierarchy;
+mod planner;
+pub mod render;
+pub mod replay;
+pub mod script;
+pub mod sensor;
+pub mod simulation;
+pub mod snapshot;
+pub mod wasm_brain;
@@ -1,569 +1,379 @@
+use clap::Parser;
+use ggez::event::{KeyCode, KeyMods};
 use ggez::{conf, event, graphics, Context, GameResult};
-use noise::{NoiseFn, Perlin};
-use rand::seq::SliceRandom;
-use rand::{rngs::StdRng, Rng, SeedableRng};
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
-
-#[derive(Clone, Debug, PartialEq)]
-enum Cell {
-    Empty,
-    Obstacle,
-    Energy,
-    Crystal,
-    Base,
-    ReservedEnergy,
-    ReservedCrystal,
+use rand::Rng;
+use rust_game::capture::{self, Capture};
+use rust_game::config::{ConfigError, RobotConfig, SimulationConfig};
+use rust_game::replay::{self, Recorder, Replay};
+use rust_game::simulation::{Exploration, Movement, Simulation, Topology};
+use rust_game::{ascii_map, batch, render, snapshot};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Taille d'une case à l'écran, en pixels, quand la carte tient dans l'écran
+const CELL_SIZE: f32 = 20.0;
+// Part de l'écran que la fenêtre peut occuper, pour laisser la place aux bordures et barres
+const SCREEN_SHARE: f32 = 0.9;
+// Écran supposé quand le moniteur n'est pas connu
+const FALLBACK_SCREEN: (f32, f32) = (1280.0, 720.0);
+
+// Limite utilisée en mode headless quand --max-ticks n'est pas fourni
+const DEFAULT_HEADLESS_MAX_TICKS: u64 = 10_000;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "rust-game",
+    about = "Simulation de robots explorateurs et extracteurs"
+)]
+struct Cli {
+    /// Fichier de scénario TOML, les autres options le surchargent
+    #[arg(long)]
+    scenario: Option<PathBuf>,
+    /// Seed de la partie (celle du scénario, sinon aléatoire)
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Largeur de la carte en cases [défaut: 40]
+    #[arg(long)]
+    width: Option<usize>,
+    /// Hauteur de la carte en cases [défaut: 30]
+    #[arg(long)]
+    height: Option<usize>,
+    /// Nombre de robots explorateurs [défaut: 3]
+    #[arg(long)]
+    robots: Option<usize>,
+    /// Nombre de ressources d'énergie [défaut: 5]
+    #[arg(long)]
+    energy: Option<usize>,
+    /// Nombre de cristaux [défaut: 10]
+    #[arg(long)]
+    crystals: Option<usize>,
+    /// Forme de la carte : bounded (bords infranchissables) ou torus [défaut: torus]
+    #[arg(long)]
+    topology: Option<Topology>,
+    /// Déplacements des robots : four (4 directions) ou eight (avec les diagonales) [défaut: four]
+    #[arg(long)]
+    movement: Option<Movement>,
+    /// Exploration : frontier (vers le brouillard le plus proche) ou random (marche au hasard) [défaut: frontier]
+    #[arg(long)]
+    exploration: Option<Exploration>,
+    /// Portée radio des robots en cases : chacun a sa carte et ne la partage qu'à portée [défaut: illimitée]
+    #[arg(long, value_name = "CASES")]
+    communication_range: Option<usize>,
+    /// Carte ASCII dessinée à la main (# obstacle, E énergie, C cristal, B base, . vide, = route, : sable, ^ roche, ~ boue)
+    #[arg(long, value_name = "FICHIER")]
+    map: Option<PathBuf>,
+    /// Écrit la carte de départ au format ASCII
+    #[arg(long, value_name = "FICHIER")]
+    export_map: Option<PathBuf>,
+    /// Seuil du bruit au-dessus duquel une case devient un obstacle [défaut: 0.5]
+    #[arg(long, allow_negative_numbers = true)]
+    noise_threshold: Option<f64>,
+    /// Lance la simulation sans fenêtre
+    #[arg(long)]
+    headless: bool,
+    /// Arrête la partie après ce nombre de ticks
+    #[arg(long)]
+    max_ticks: Option<u64>,
+    /// Simule COUNT seeds à partir de --seed, sans fenêtre, et écrit les statistiques
+    #[arg(long, value_name = "COUNT")]
+    batch: Option<u64>,
+    /// Dossier de sortie du batch (runs.csv, summary.csv, coverage.csv, batch.json)
+    #[arg(long, default_value = "batch_results")]
+    out: PathBuf,
+    /// Écrit l'état final de la partie headless en PNG
+    #[arg(long, value_name = "FICHIER")]
+    png: Option<PathBuf>,
+    /// Écrit en PNG le champ de distances vers la base à la fin de la partie headless
+    #[arg(long, value_name = "FICHIER")]
+    flow_png: Option<PathBuf>,
+    /// Écrit en PNG la carte que croit connaître le robot --belief-robot à la fin de la partie headless
+    #[arg(long, value_name = "FICHIER")]
+    belief_png: Option<PathBuf>,
+    /// Robot dont --belief-png montre la carte, en partant de 0
+    #[arg(long, default_value_t = 0)]
+    belief_robot: usize,
+    /// Taille d'une case dans les PNG exportés, en pixels
+    #[arg(long, default_value_t = render::DEFAULT_PIXELS_PER_CELL, value_parser = clap::value_parser!(u32).range(1..))]
+    pixels_per_cell: u32,
+    /// Écrit aussi une miniature PNG de l'état final de chaque seed du batch
+    #[arg(long)]
+    thumbnails: bool,
+    /// Capture la partie headless en GIF animé (FICHIER.gif) ou en PNG numérotés dans un dossier
+    #[arg(long, value_name = "FICHIER", requires = "headless")]
+    capture: Option<PathBuf>,
+    /// Nombre de ticks entre deux images capturées
+    #[arg(long, default_value_t = capture::DEFAULT_CAPTURE_INTERVAL, value_parser = clap::value_parser!(u64).range(1..))]
+    capture_every: u64,
+    /// Reprend une partie depuis un snapshot (les options de carte sont ignorées)
+    #[arg(long, value_name = "SNAPSHOT")]
+    resume: Option<PathBuf>,
+    /// Dossier où la touche S enregistre les snapshots
+    #[arg(long, default_value = ".")]
+    snapshot_dir: PathBuf,
+    /// Enregistre la partie dans un fichier de replay
+    #[arg(long, value_name = "REPLAY", conflicts_with = "resume")]
+    record: Option<PathBuf>,
+    /// Rejoue un replay sans fenêtre et vérifie qu'il se déroule à l'identique
+    #[arg(long, value_name = "REPLAY")]
+    replay: Option<PathBuf>,
 }
 
-#[derive(Clone, Debug)]
-struct Robot {
-    x: usize,
-    y: usize,
-    role: Role,
-    resource_coords: Option<(usize, usize)>,
-    carrying: Option<Cell>,
-    speed: usize,
-    move_counter: usize,
-}
+impl Cli {
+    fn simulation_config(&self) -> Result<SimulationConfig, ConfigError> {
+        let mut config = match &self.scenario {
+            Some(path) => SimulationConfig::load(path)?,
+            None => SimulationConfig {
+                seed: rand::thread_rng().gen(),
+                ..SimulationConfig::default()
+            },
+        };
 
-impl Robot {
-    fn default_speed() -> usize {
-        1
-    }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        if let Some(width) = self.width {
+            config.map_width = width;
+        }
+        if let Some(height) = self.height {
+            config.map_height = height;
+        }
+        if let Some(robots) = self.robots {
+            config.robots = vec![RobotConfig::default(); robots];
+        }
+        if let Some(energy) = self.energy {
+            config.energy_count = energy;
+        }
+        if let Some(crystals) = self.crystals {
+            config.crystal_count = crystals;
+        }
+        if let Some(noise_threshold) = self.noise_threshold {
+            config.noise_threshold = noise_threshold;
+        }
+        if let Some(max_ticks) = self.max_ticks {
+            config.end.max_ticks = Some(max_ticks);
+        }
+        if let Some(topology) = self.topology {
+            config.topology = topology;
+        }
+        if let Some(movement) = self.movement {
+            config.movement = movement;
+        }
+        if let Some(exploration) = self.exploration {
+            config.exploration = exploration;
+        }
+        if self.communication_range.is_some() {
+            config.communication_range = self.communication_range;
+        }
+        if let Some(path) = &self.map {
+            config.load_map_layout(path)?;
+        }
 
-    fn increased_speed() -> usize {
-        4
+        config.validate()?;
+        Ok(config)
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-enum Role {
-    Explorer,
-    Extractor,
-}
-
-#[derive(Eq, PartialEq)]
-struct Node {
-    cost: usize,
-    position: (usize, usize),
-    priority: usize,
+// Rendu ggez au-dessus de la simulation
+struct GameState {
+    simulation: Simulation,
+    paused: bool,
+    // Affiche le champ de distances vers la base à la place du terrain
+    show_flow_field: bool,
+    // Affiche l'action en cours de l'arbre de comportement de chaque robot
+    show_behaviour: bool,
+    // Robot dont on affiche la carte à la place de celle de la base
+    belief: Option<usize>,
+    // Taille d'une case en pixels, réduite sur les grandes cartes
+    cell_size: f32,
+    // Carte affichée, un pixel RGBA par case, et sa texture : elle n'est renvoyée à la carte
+    // graphique que lorsqu'une case change de couleur
+    map_pixels: Vec<u8>,
+    map_image: Option<graphics::Image>,
+    snapshot_dir: PathBuf,
+    recording: Option<Recording>,
 }
 
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.priority.cmp(&self.priority)
-    }
+// Replay en cours d'enregistrement et fichier où l'écrire
+struct Recording {
+    recorder: Recorder,
+    path: PathBuf,
 }
 
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl Recording {
+    fn save(self) {
+        match self.recorder.finish().save(&self.path) {
+            Ok(()) => println!("Replay enregistré dans {}", self.path.display()),
+            Err(err) => eprintln!("Erreur: {}", err),
+        }
     }
 }
 
-struct GameState {
-    map: Vec<Vec<Cell>>,
-    map_width: usize,
-    map_height: usize,
-    base_position: (usize, usize),
-    robots: Vec<Robot>,
-    crystal_score: u32,
-    energy_score: u32,
-    game_over: bool,
-    discovered: Vec<Vec<bool>>,
-}
-
 impl GameState {
-    fn new(_ctx: &mut Context, seed: u64) -> GameResult<GameState> {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let noise = Perlin::new();
-        let map_width = 40;
-        let map_height = 30;
-        let noise_threshold = 0.5;
-        let mut map = vec![vec![Cell::Empty; map_width]; map_height];
-        let discovered = vec![vec![false; map_width]; map_height];
-
-        // Generation d'obstacle aléatoire
-        for y in 0..map_height {
-            for x in 0..map_width {
-                let nx = x as f64 / map_width as f64;
-                let ny = y as f64 / map_height as f64;
-                let val = noise.get([nx * 10.0, ny * 10.0, rng.gen::<f64>()]);
-                if val > noise_threshold {
-                    map[y][x] = Cell::Obstacle;
-                }
-            }
-        }
-
-        // creation d'obstacles pour la bordure de map
-        for x in 0..map_width {
-            map[0][x] = Cell::Obstacle;
-            map[map_height - 1][x] = Cell::Obstacle;
-        }
-        for y in 0..map_height {
-            map[y][0] = Cell::Obstacle;
-            map[y][map_width - 1] = Cell::Obstacle;
-        }
-
-        // Place la base à une position random
-        let base_position = place_randomly(&mut map, &mut rng, Cell::Base, 1).unwrap();
-
-        // enleve les obstacles autour de la base
-        for dy in -3..=3 {
-            for dx in -3..=3 {
-                let (x, y) = (
-                    (base_position.0 as isize + dx).clamp(0, map_width as isize - 1) as usize,
-                    (base_position.1 as isize + dy).clamp(0, map_height as isize - 1) as usize,
-                );
-                map[y][x] = Cell::Empty;
-            }
+    fn new(
+        simulation: Simulation,
+        cell_size: f32,
+        snapshot_dir: PathBuf,
+        recording: Option<Recording>,
+    ) -> GameState {
+        GameState {
+            simulation,
+            paused: false,
+            show_flow_field: false,
+            show_behaviour: false,
+            belief: None,
+            cell_size,
+            map_pixels: Vec::new(),
+            map_image: None,
+            snapshot_dir,
+            recording,
         }
-
-        // Place aleatoirement les ressources
-        place_randomly(&mut map, &mut rng, Cell::Energy, 5);
-        place_randomly(&mut map, &mut rng, Cell::Crystal, 10);
-
-        // S'assurer que la base à une bonne position
-        map[base_position.1][base_position.0] = Cell::Base;
-
-        // Initialisation des 3 robots
-        let robots = vec![
-            Robot {
-                x: base_position.0,
-                y: base_position.1,
-                role: Role::Explorer,
-                resource_coords: None,
-                carrying: None,
-                speed: Robot::default_speed(),
-                move_counter: 0,
-            },
-            Robot {
-                x: base_position.0,
-                y: base_position.1,
-                role: Role::Explorer,
-                resource_coords: None,
-                carrying: None,
-                speed: Robot::default_speed(),
-                move_counter: 0,
-            },
-            Robot {
-                x: base_position.0,
-                y: base_position.1,
-                role: Role::Explorer,
-                resource_coords: None,
-                carrying: None,
-                speed: Robot::default_speed(),
-                move_counter: 0,
-            },
-        ];
-
-        Ok(GameState {
-            map,
-            map_width,
-            map_height,
-            base_position,
-            robots,
-            crystal_score: 0,
-            energy_score: 0,
-            game_over: false,
-            discovered,
-        })
-    }
-
-    fn wrap_position(&self, x: usize, y: usize) -> (usize, usize) {
-        let wrapped_x = (x + self.map_width) % self.map_width;
-        let wrapped_y = (y + self.map_height) % self.map_height;
-        (wrapped_x, wrapped_y)
     }
 
-    fn a_star_pathfinding(
-        &self,
-        start: (usize, usize),
-        goal: (usize, usize),
-        avoid_fog: bool,
-    ) -> Option<Vec<(usize, usize)>> {
-        let mut open_set = BinaryHeap::new();
-        let mut came_from = std::collections::HashMap::new();
-        let mut g_score = std::collections::HashMap::new();
-        let mut f_score = std::collections::HashMap::new();
-        let mut closed_set = HashSet::new();
-
-        open_set.push(Node {
-            cost: 0,
-            position: start,
-            priority: Self::heuristic(start, goal),
-        });
-
-        g_score.insert(start, 0);
-        f_score.insert(start, Self::heuristic(start, goal));
-
-        let directions = [(-1, 0), (1, 0), (0, -1), (0, 1)];
-
-        while let Some(Node { position, .. }) = open_set.pop() {
-            if position == goal {
-                let mut path = vec![position];
-                let mut current = position;
-
-                while let Some(&prev) = came_from.get(&current) {
-                    current = prev;
-                    path.push(current);
-                }
-
-                path.reverse();
-                return Some(path);
-            }
-
-            closed_set.insert(position);
-
-            for (dx, dy) in directions.iter() {
-                let new_x = (position.0 as isize + dx).max(0) as usize;
-                let new_y = (position.1 as isize + dy).max(0) as usize;
-                let wrapped_pos = self.wrap_position(new_x, new_y);
-
-                if closed_set.contains(&wrapped_pos)
-                    || matches!(self.map[wrapped_pos.1][wrapped_pos.0], Cell::Obstacle)
-                    || (avoid_fog && !self.discovered[wrapped_pos.1][wrapped_pos.0])
-                {
-                    continue;
-                }
-
-                let tentative_g_score = g_score.get(&position).unwrap_or(&usize::MAX) + 1;
-
-                if tentative_g_score < *g_score.get(&wrapped_pos).unwrap_or(&usize::MAX) {
-                    came_from.insert(wrapped_pos, position);
-                    g_score.insert(wrapped_pos, tentative_g_score);
-                    f_score.insert(
-                        wrapped_pos,
-                        tentative_g_score + Self::heuristic(wrapped_pos, goal),
-                    );
-
-                    open_set.push(Node {
-                        cost: tentative_g_score,
-                        position: wrapped_pos,
-                        priority: tentative_g_score + Self::heuristic(wrapped_pos, goal),
-                    });
-                }
-            }
-        }
-
-        None
-    }
-
-    fn heuristic(a: (usize, usize), b: (usize, usize)) -> usize {
-        let dx = (a.0 as isize - b.0 as isize).abs() as usize;
-        let dy = (a.1 as isize - b.1 as isize).abs() as usize;
-        dx + dy
-    }
-
-    fn move_robot_towards_target(
-        &self,
-        x: usize,
-        y: usize,
-        target_x: usize,
-        target_y: usize,
-        avoid_fog: bool,
-    ) -> (usize, usize) {
-        let valid_positions = |pos: (usize, usize)| {
-            !matches!(self.map[pos.1][pos.0], Cell::Obstacle)
-                && (!avoid_fog || self.discovered[pos.1][pos.0])
+    // Couleur de chaque case : champ de distances, terrain ou brouillard
+    fn map_pixels(&self) -> Vec<u8> {
+        let simulation = &self.simulation;
+        let flow_field = simulation
+            .flow_field()
+            .filter(|_| self.show_flow_field)
+            .map(|field| (field, field.max_distance().unwrap_or(0)));
+        let known = match self.belief {
+            Some(robot) => simulation.knowledge(robot),
+            None => &simulation.discovered[..],
         };
-
-        if let Some(path) = self.a_star_pathfinding((x, y), (target_x, target_y), avoid_fog) {
-            if path.len() > 1 && valid_positions(path[1]) {
-                return path[1];
+        let mut pixels = Vec::with_capacity(simulation.map_width * simulation.map_height * 4);
+        for (y, row) in simulation.map.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let flow_distance = flow_field.and_then(|(field, max_distance)| {
+                    Some((field.distance((x, y))?, max_distance))
+                });
+                let [r, g, b] = if let Some((distance, max_distance)) = flow_distance {
+                    render::flow_color(distance, max_distance)
+                } else if known[y][x] {
+                    let color = render::ground_color(cell, simulation.terrain[y][x]);
+                    if simulation.is_visible((x, y)) {
+                        color
+                    } else {
+                        render::stale(color)
+                    }
+                } else {
+                    [0, 0, 0]
+                };
+                pixels.extend_from_slice(&[r, g, b, 255]);
             }
         }
-        (x, y)
+        pixels
     }
 
-    fn move_robot_randomly(
-        x: usize,
-        y: usize,
-        directions: &[(isize, isize)],
-        map: &Vec<Vec<Cell>>,
-        map_width: usize,
-        map_height: usize,
-    ) -> (usize, usize) {
-        if let Some(&(dx, dy)) = directions.choose(&mut rand::thread_rng()) {
-            let new_x = (x as isize + dx).max(0) as usize % map_width;
-            let new_y = (y as isize + dy).max(0) as usize % map_height;
-            if matches!(
-                map[new_y][new_x],
-                Cell::Empty | Cell::Energy | Cell::Crystal | Cell::Base
-            ) {
-                return (new_x, new_y);
-            }
+    fn map_image(&mut self, ctx: &mut Context) -> GameResult<&graphics::Image> {
+        let pixels = self.map_pixels();
+        if self.map_image.is_none() || pixels != self.map_pixels {
+            let size = |cells: usize| {
+                u16::try_from(cells).map_err(|_| {
+                    ggez::GameError::RenderError(format!("Carte trop grande: {} cases", cells))
+                })
+            };
+            let (width, height) = (
+                size(self.simulation.map_width)?,
+                size(self.simulation.map_height)?,
+            );
+            let mut image = graphics::Image::from_rgba8(ctx, width, height, &pixels)?;
+            image.set_filter(graphics::FilterMode::Nearest);
+            self.map_image = Some(image);
+            self.map_pixels = pixels;
         }
-        (x, y)
+        Ok(self.map_image.as_ref().expect("texture créée ci-dessus"))
     }
 
-    fn update_robot(&mut self, robot: &mut Robot) {
-        if robot.move_counter < robot.speed {
-            robot.move_counter += 1;
-            return;
-        }
-        robot.move_counter = 0;
-
-        let directions = [(-1, 0), (1, 0), (0, -1), (0, 1)];
-
-        match robot.role {
-            Role::Explorer => {
-                if let Some(resource_coords) = robot.resource_coords {
-                    let (new_x, new_y) = self.move_robot_towards_target(
-                        robot.x,
-                        robot.y,
-                        self.base_position.0,
-                        self.base_position.1,
-                        false,
-                    );
-                    robot.x = new_x;
-                    robot.y = new_y;
-
-                    if (robot.x, robot.y) == self.base_position {
-                        // Robot explorateur passe à robot extracteur et va chercher la ressource
-                        robot.role = Role::Extractor;
-                        println!(
-                            "Déploiement du Robot extracteur, il part chercher la ressource {:?}",
-                            resource_coords
-                        );
-                    }
-                } else {
-                    // Explore la map et se rappelle de la postion des ressources
-                    let (new_x, new_y) = GameState::move_robot_randomly(
-                        robot.x,
-                        robot.y,
-                        &directions,
-                        &self.map,
-                        self.map_width,
-                        self.map_height,
-                    );
-                    robot.x = new_x;
-                    robot.y = new_y;
-
-                    // Marque la position actuelle comme découverte
-                    for dy in -1..=1 {
-                        for dx in -1..=1 {
-                            let (disc_x, disc_y) = (
-                                (robot.x as isize + dx).clamp(0, self.map_width as isize - 1)
-                                    as usize,
-                                (robot.y as isize + dy).clamp(0, self.map_height as isize - 1)
-                                    as usize,
-                            );
-                            self.discovered[disc_y][disc_x] = true;
-                        }
-                    }
-
-                    // Check si la ressource est autour du robot
-                    for dy in -1..=1 {
-                        for dx in -1..=1 {
-                            let (check_x, check_y) = (
-                                (robot.x as isize + dx).clamp(0, self.map_width as isize - 1)
-                                    as usize,
-                                (robot.y as isize + dy).clamp(0, self.map_height as isize - 1)
-                                    as usize,
-                            );
-                            if matches!(self.map[check_y][check_x], Cell::Crystal | Cell::Energy) {
-                                let resource_type = self.map[check_y][check_x].clone();
-                                robot.resource_coords = Some((check_x, check_y));
-                                self.map[check_y][check_x] = match resource_type {
-                                    Cell::Crystal => Cell::ReservedCrystal,
-                                    Cell::Energy => Cell::ReservedEnergy,
-                                    _ => unreachable!(),
-                                };
-                                println!("Robot explorateur à trouver une ressource {:?}, retour à la base", (check_x, check_y));
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-            Role::Extractor => {
-                if let Some(resource_coords) = robot.resource_coords {
-                    if robot.carrying.is_none() {
-                        // Va chercher la ressource
-                        let (new_x, new_y) = self.move_robot_towards_target(
-                            robot.x,
-                            robot.y,
-                            resource_coords.0,
-                            resource_coords.1,
-                            true,
-                        );
-                        robot.x = new_x;
-                        robot.y = new_y;
-
-                        if (robot.x, robot.y) == resource_coords {
-                            // Collecter la ressource
-                            robot.speed = Robot::increased_speed();
-                            if matches!(
-                                self.map[robot.y][robot.x],
-                                Cell::ReservedCrystal | Cell::ReservedEnergy
-                            ) {
-                                robot.carrying = Some(match self.map[robot.y][robot.x] {
-                                    Cell::ReservedCrystal => Cell::Crystal,
-                                    Cell::ReservedEnergy => Cell::Energy,
-                                    _ => unreachable!(),
-                                });
-                                self.map[robot.y][robot.x] = Cell::Empty;
-                                println!("Robot extracteur a récupéré la ressource {:?}, retour à la base", (robot.x, robot.y));
-                            }
-                        }
-                    } else {
-                        // REtourne à la base apres avoir extrait
-                        let (new_x, new_y) = self.move_robot_towards_target(
-                            robot.x,
-                            robot.y,
-                            self.base_position.0,
-                            self.base_position.1,
-                            true,
-                        );
-                        robot.x = new_x;
-                        robot.y = new_y;
-
-                        if (robot.x, robot.y) == self.base_position {
-                            match robot.carrying {
-                                Some(Cell::Crystal) => {
-                                    self.crystal_score += 1;
-                                    println!(
-                                        "Cristal déposé à la base. Score: {}",
-                                        self.crystal_score
-                                    );
-                                }
-                                Some(Cell::Energy) => {
-                                    self.energy_score += 1;
-                                    println!(
-                                        "Energie déposée à la base. Score: {}",
-                                        self.energy_score
-                                    );
-                                }
-                                _ => {}
-                            }
-                            // Passe de l'extracteur à l'explorateur
-                            robot.role = Role::Explorer;
-                            robot.speed = Robot::default_speed();
-                            robot.carrying = None;
-                            robot.resource_coords = None;
-                            println!("Envoie du robot explorateur");
-                        }
-                    }
-                }
-            }
+    fn step(&mut self) {
+        let actions = self.simulation.step();
+        if let Some(recording) = &mut self.recording {
+            recording.recorder.record(&self.simulation, &actions);
+        }
+        if self.simulation.game_over {
+            self.finish_recording();
         }
     }
 
-    fn check_game_over(&self) -> bool {
-        !self.map.iter().any(|row| {
-            row.iter().any(|cell| {
-                matches!(
-                    cell,
-                    Cell::Crystal | Cell::Energy | Cell::ReservedCrystal | Cell::ReservedEnergy
-                )
-            })
-        })
-    }
-}
-
-fn place_randomly(
-    map: &mut Vec<Vec<Cell>>,
-    rng: &mut StdRng,
-    cell_type: Cell,
-    quantity: usize,
-) -> Option<(usize, usize)> {
-    let map_height = map.len();
-    let map_width = map[0].len();
-
-    let mut placed_positions = Vec::new();
-
-    for _ in 0..quantity {
-        let mut placed = false;
-        while !placed {
-            let x = rng.gen_range(0..map_width);
-            let y = rng.gen_range(0..map_height);
-            if matches!(map[y][x], Cell::Empty) {
-                map[y][x] = cell_type.clone();
-                placed_positions.push((x, y));
-                placed = true;
-            }
+    fn finish_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            recording.save();
         }
     }
 
-    if !placed_positions.is_empty() {
-        Some(placed_positions[0])
-    } else {
-        None
+    fn save_snapshot(&self) {
+        let path = self
+            .snapshot_dir
+            .join(format!("snapshot_tick_{}.json", self.simulation.tick));
+        match snapshot::save(&self.simulation, &path) {
+            Ok(()) => println!("Snapshot enregistré dans {}", path.display()),
+            Err(err) => eprintln!("Erreur: {}", err),
+        }
     }
 }
 
 impl event::EventHandler<ggez::GameError> for GameState {
     fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        if self.game_over {
-            return Ok(());
-        }
-
-        // Récuperer les mouvements séparément
-        let mut updated_robots = self.robots.clone();
-
-        for robot in &mut updated_robots {
-            self.update_robot(robot);
+        if !self.paused {
+            self.step();
         }
+        Ok(())
+    }
 
-        self.robots = updated_robots;
-
-        // Check si le jeu est finis
-        self.game_over = self.check_game_over();
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.finish_recording();
+        false
+    }
 
-        if self.game_over {
-            println!(
-                "Fin du jeu! Score final - Cristaux: {}, Energies: {}",
-                self.crystal_score, self.energy_score
-            );
+    // S : snapshot, Espace : pause, N : avance d'un tick pendant la pause,
+    // F : champ de distances vers la base, B : action de l'arbre de comportement des robots,
+    // 1 à 9 : carte connue d'un robot, 0 : carte de la base
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        match keycode {
+            KeyCode::Escape => {
+                self.finish_recording();
+                event::quit(ctx);
+            }
+            KeyCode::S if !repeat => self.save_snapshot(),
+            KeyCode::Space if !repeat => self.paused = !self.paused,
+            KeyCode::N if self.paused => self.step(),
+            KeyCode::F if !repeat => self.show_flow_field = !self.show_flow_field,
+            KeyCode::B if !repeat => self.show_behaviour = !self.show_behaviour,
+            KeyCode::Key0 if !repeat => self.belief = None,
+            _ if !repeat => {
+                if let Some(robot) = robot_key(keycode) {
+                    if robot < self.simulation.robots.len() {
+                        self.belief = Some(robot);
+                    }
+                }
+            }
+            _ => {}
         }
-
-        Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let cell_size = self.cell_size;
         graphics::clear(ctx, graphics::Color::new(0.0, 0.0, 0.0, 1.0));
-        let cell_size = 20.0;
-        for (y, row) in self.map.iter().enumerate() {
-            for (x, cell) in row.iter().enumerate() {
-                let color = if self.discovered[y][x] {
-                    match cell {
-                        Cell::Obstacle => graphics::Color::new(0.5, 0.5, 0.5, 1.0),
-                        Cell::Energy => graphics::Color::new(1.0, 1.0, 0.0, 1.0),
-                        Cell::Crystal => graphics::Color::new(0.5, 0.0, 0.5, 1.0),
-                        Cell::Base => graphics::Color::new(1.0, 0.0, 0.0, 1.0),
-                        Cell::ReservedEnergy => graphics::Color::new(1.0, 1.0, 0.5, 1.0),
-                        Cell::ReservedCrystal => graphics::Color::new(0.75, 0.0, 0.75, 1.0),
-                        Cell::Empty => graphics::Color::new(0.0, 0.8, 0.0, 1.0),
-                    }
-                } else {
-                    graphics::Color::new(0.0, 0.0, 0.0, 1.0)
-                };
-
-                let rectangle = graphics::Mesh::new_rectangle(
-                    ctx,
-                    graphics::DrawMode::fill(),
-                    graphics::Rect::new(
-                        x as f32 * cell_size,
-                        y as f32 * cell_size,
-                        cell_size,
-                        cell_size,
-                    ),
-                    color,
-                )?;
-
-                graphics::draw(ctx, &rectangle, graphics::DrawParam::default())?;
-            }
-        }
+        let map_image = self.map_image(ctx)?;
+        graphics::draw(
+            ctx,
+            map_image,
+            graphics::DrawParam::default().scale([cell_size, cell_size]),
+        )?;
 
-        for robot in &self.robots {
-            let color = match robot.role {
-                Role::Explorer => graphics::Color::new(0.0, 0.0, 1.0, 1.0), //Robot explorateur
-                Role::Extractor => graphics::Color::new(1.0, 0.65, 0.0, 1.0), // Robot extracteur
-            };
-            let robot_rectangle = graphics::Mesh::new_rectangle(
-                ctx,
+        let simulation = &self.simulation;
+        let mut robots = graphics::MeshBuilder::new();
+        for robot in &simulation.robots {
+            let [r, g, b] = render::robot_state_color(robot);
+            robots.rectangle(
                 graphics::DrawMode::fill(),
                 graphics::Rect::new(
                     robot.x as f32 * cell_size,
@@ -571,16 +381,42 @@ impl event::EventHandler<ggez::GameError> for GameState {
                     cell_size,
                     cell_size,
                 ),
-                color,
+                graphics::Color::from_rgb(r, g, b),
             )?;
-            graphics::draw(ctx, &robot_rectangle, graphics::DrawParam::default())?;
+        }
+        if !simulation.robots.is_empty() {
+            let robots = robots.build(ctx)?;
+            graphics::draw(ctx, &robots, graphics::DrawParam::default())?;
+        }
+
+        if self.show_behaviour {
+            for robot in &simulation.robots {
+                let Some(label) = robot.active_node.as_ref().and_then(|path| {
+                    simulation
+                        .behaviour_trees
+                        .get(&robot.strategy)?
+                        .describe(path)
+                }) else {
+                    continue;
+                };
+                let label = graphics::Text::new((label, graphics::Font::default(), 12.0));
+                graphics::draw(
+                    ctx,
+                    &label,
+                    graphics::DrawParam::default()
+                        .dest([(robot.x + 1) as f32 * cell_size, robot.y as f32 * cell_size]),
+                )?;
+            }
         }
 
         //Affiche le score
-        let score_text = format!(
+        let mut score_text = format!(
             "Cistaux: {} | Energies: {}",
-            self.crystal_score, self.energy_score
+            simulation.crystal_score, simulation.energy_score
         );
+        if let Some(robot) = self.belief {
+            score_text.push_str(&format!(" | Carte du robot {}", robot));
+        }
         let score_display = graphics::Text::new((score_text, graphics::Font::default(), 18.0));
         graphics::draw(
             ctx,
@@ -588,189 +424,298 @@ impl event::EventHandler<ggez::GameError> for GameState {
             graphics::DrawParam::default().dest([10.0, 10.0]),
         )?;
 
+        // Erreurs des scripts, en rouge sous le score
+        let script_errors = simulation
+            .robots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, robot)| Some((index, robot, robot.script_error.as_ref()?)));
+        for (line, (index, robot, err)) in script_errors.enumerate() {
+            let error_text = format!("Robot {} ({}): {}", index, robot.strategy, err);
+            let error_display = graphics::Text::new((error_text, graphics::Font::default(), 14.0));
+            graphics::draw(
+                ctx,
+                &error_display,
+                graphics::DrawParam::default()
+                    .dest([10.0, 34.0 + line as f32 * 18.0])
+                    .color(graphics::Color::from_rgb(255, 80, 80)),
+            )?;
+        }
+
         graphics::present(ctx)?;
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use assert_approx_eq::assert_approx_eq;
-    use rstest::rstest;
-    use ggez::conf;
-
-    fn create_test_context() -> (Context, GameState) {
-        let seed = 0;
-        let cb = ggez::ContextBuilder::new("Rust Game Test", "ggez")
-            .window_setup(conf::WindowSetup::default().title("Test"))
-            .window_mode(conf::WindowMode::default().dimensions(800.0, 600.0));
-        let (mut ctx, event_loop) = cb.build().unwrap();
-        let state = GameState::new(&mut ctx, seed).unwrap();
-        (ctx, state)
-    }
+// Touches 1 à 9 : robots 0 à 8
+fn robot_key(keycode: KeyCode) -> Option<usize> {
+    let robot = match keycode {
+        KeyCode::Key1 => 0,
+        KeyCode::Key2 => 1,
+        KeyCode::Key3 => 2,
+        KeyCode::Key4 => 3,
+        KeyCode::Key5 => 4,
+        KeyCode::Key6 => 5,
+        KeyCode::Key7 => 6,
+        KeyCode::Key8 => 7,
+        KeyCode::Key9 => 8,
+        _ => return None,
+    };
+    Some(robot)
+}
 
-    #[test]
-    fn test_heuristic() {
-        assert_eq!(GameState::heuristic((0, 0), (3, 4)), 7);
-        assert_eq!(GameState::heuristic((2, 3), (5, 1)), 5);
+fn run_headless(
+    mut simulation: Simulation,
+    mut recording: Option<Recording>,
+    mut capture: Option<Capture>,
+) -> Simulation {
+    if let Some(capture) = &mut capture {
+        or_exit(capture.record(&simulation));
     }
-
-    #[test]
-    fn test_wrap_position() {
-        let (mut ctx, game_state) = create_test_context();
-        assert_eq!(game_state.wrap_position(39, 29), (39, 29));
-        assert_eq!(game_state.wrap_position(40, 30), (0, 0));
-        assert_eq!(game_state.wrap_position(41, 31), (1, 1));
+    while !simulation.game_over {
+        let actions = simulation.step();
+        if let Some(recording) = &mut recording {
+            recording.recorder.record(&simulation, &actions);
+        }
+        if let Some(capture) = &mut capture {
+            or_exit(capture.record(&simulation));
+        }
     }
-
-    #[test]
-    fn test_move_robot_randomly() {
-        let map = vec![vec![Cell::Empty; 5]; 5];
-        let directions = [(-1, 0), (1, 0), (0, -1), (0, 1)];
-        let (x, y) = GameState::move_robot_randomly(2, 2, &directions, &map, 5, 5);
-        assert!(x < 5 && y < 5);
+    println!(
+        "Partie terminée en {} ticks - Cristaux: {}, Energies: {}",
+        simulation.tick, simulation.crystal_score, simulation.energy_score
+    );
+    if let Some(recording) = recording {
+        recording.save();
     }
-
-    #[rstest]
-    #[case(Cell::Energy, true)]
-    #[case(Cell::Crystal, true)]
-    #[case(Cell::Obstacle, false)]
-    fn test_place_randomly(#[case] cell_type: Cell, #[case] expected: bool) {
-        let mut map = vec![vec![Cell::Empty; 5]; 5];
-        let mut rng = StdRng::seed_from_u64(0);
-        let result = place_randomly(&mut map, &mut rng, cell_type.clone(), 1);
-        assert_eq!(result.is_some(), expected);
-        if let Some((x, y)) = result {
-            assert_eq!(map[y][x], cell_type);
-        }
+    if let Some(capture) = capture {
+        println!("{} images capturées", capture.finish());
     }
+    simulation
+}
 
-    #[test]
-    fn test_a_star_pathfinding() {
-        let (mut ctx, mut game_state) = create_test_context();
-        game_state.map[1][0] = Cell::Obstacle;
-        game_state.map[1][1] = Cell::Obstacle;
-        game_state.map[1][2] = Cell::Obstacle;
-        game_state.map[0][2] = Cell::Obstacle;
-
-        if let Some(path) = game_state.a_star_pathfinding((0, 0), (2, 2), false) {
-            assert_eq!(path, vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]);
-        } else {
-            panic!("Pathfinding failed");
+fn run_replay_mode(path: &Path) {
+    let replay = or_exit(Replay::load(path));
+    println!(
+        "Replay de la seed {} ({} ticks)",
+        replay.config.seed,
+        replay.ticks.len()
+    );
+    match replay.verify() {
+        Ok(ticks) => println!("Replay identique sur {} ticks", ticks),
+        Err(err) => {
+            eprintln!("Erreur: {}", err);
+            process::exit(1);
         }
     }
+}
 
-    #[test]
-    fn test_update_robot() {
-        let (mut ctx, mut game_state) = create_test_context();
-        let mut robot = Robot {
-            x: game_state.base_position.0,
-            y: game_state.base_position.1,
-            role: Role::Explorer,
-            resource_coords: None,
-            carrying: None,
-            speed: Robot::default_speed(),
-            move_counter: 0,
-        };
-
-        game_state.update_robot(&mut robot);
-        assert_eq!(robot.move_counter, 1);
-
-        game_state.update_robot(&mut robot);
-        assert!(robot.x != game_state.base_position.0 || robot.y != game_state.base_position.1);
+fn run_batch_mode(
+    config: &SimulationConfig,
+    count: u64,
+    out: &Path,
+    thumbnails: Option<&batch::Thumbnails>,
+) -> io::Result<()> {
+    let seeds = config.seed..config.seed + count;
+    println!(
+        "Batch de {} parties (seeds {} à {})",
+        count,
+        seeds.start,
+        seeds.end - 1
+    );
+
+    fs::create_dir_all(out)?;
+    if let Some(thumbnails) = thumbnails {
+        fs::create_dir_all(&thumbnails.dir)?;
     }
-
-    #[test]
-    fn test_check_game_over() {
-        let (mut ctx, mut game_state) = create_test_context();
-        assert!(!game_state.check_game_over());
-
-        game_state.map.iter_mut().flatten().for_each(|cell| *cell = Cell::Empty);
-        assert!(game_state.check_game_over());
+    let (results, errors) = batch::run_batch(config, seeds, thumbnails);
+    for (seed, err) in &errors {
+        eprintln!("Seed {} ignorée: {}", seed, err);
     }
+    let summary = batch::summarize(&results);
+
+    batch::write_runs_csv(
+        &results,
+        &mut BufWriter::new(File::create(out.join("runs.csv"))?),
+    )?;
+    batch::write_summary_csv(
+        &summary,
+        &mut BufWriter::new(File::create(out.join("summary.csv"))?),
+    )?;
+    batch::write_coverage_csv(
+        &results,
+        &mut BufWriter::new(File::create(out.join("coverage.csv"))?),
+    )?;
+    batch::write_json(
+        &results,
+        &summary,
+        &mut BufWriter::new(File::create(out.join("batch.json"))?),
+    )?;
+
+    println!(
+        "{} parties terminées sur {} - ticks moyen: {:.1}, p90: {}, cristaux moyen: {:.2}, energies moyen: {:.2}, carte découverte: {:.1}%",
+        summary.completed,
+        summary.runs,
+        summary.ticks.mean,
+        summary.ticks.p90,
+        summary.crystal_score.mean,
+        summary.energy_score.mean,
+        summary.coverage.mean * 100.0
+    );
+    println!("Résultats écrits dans {}", out.display());
+    Ok(())
+}
 
-    #[test]
-    fn test_robot_finds_resource() {
-        let (mut ctx, mut game_state) = create_test_context();
-        let mut robot = Robot {
-            x: game_state.base_position.0,
-            y: game_state.base_position.1,
-            role: Role::Explorer,
-            resource_coords: None,
-            carrying: None,
-            speed: Robot::default_speed(),
-            move_counter: 0,
-        };
-
-        game_state.map[2][2] = Cell::Crystal;
-        robot.x = 1;
-        robot.y = 1;
-        game_state.update_robot(&mut robot);
+// Affiche l'erreur et quitte, pour les erreurs de configuration au lancement
+fn or_exit<T, E: fmt::Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("Erreur: {}", err);
+        process::exit(1);
+    })
+}
 
-        assert_eq!(robot.resource_coords, Some((2, 2)));
-        assert_eq!(game_state.map[2][2], Cell::ReservedCrystal);
+fn main() -> GameResult {
+    let cli = Cli::parse();
+
+    if let Some(count) = cli.batch {
+        let mut config = or_exit(cli.simulation_config());
+        config.end.max_ticks = config.end.max_ticks.or(Some(DEFAULT_HEADLESS_MAX_TICKS));
+        let thumbnails = cli.thumbnails.then(|| batch::Thumbnails {
+            dir: cli.out.join("thumbnails"),
+            pixels_per_cell: cli.pixels_per_cell,
+        });
+        or_exit(run_batch_mode(
+            &config,
+            count,
+            &cli.out,
+            thumbnails.as_ref(),
+        ));
+        return Ok(());
     }
 
-    #[test]
-    fn test_robot_collects_and_delivers_resource() {
-        let (mut ctx, mut game_state) = create_test_context();
-        let mut robot = Robot {
-            x: game_state.base_position.0,
-            y: game_state.base_position.1,
-            role: Role::Extractor,
-            resource_coords: Some((2, 2)),
-            carrying: None,
-            speed: Robot::default_speed(),
-            move_counter: 0,
-        };
-
-        game_state.map[2][2] = Cell::ReservedCrystal;
-        robot.x = 2;
-        robot.y = 2;
-        game_state.update_robot(&mut robot);
-
-        assert_eq!(robot.carrying, Some(Cell::Crystal));
-        assert_eq!(game_state.map[2][2], Cell::Empty);
-
-        robot.x = 1;
-        robot.y = 1;
-        game_state.update_robot(&mut robot);
-        robot.x = game_state.base_position.0;
-        robot.y = game_state.base_position.1;
-        game_state.update_robot(&mut robot);
-
-        assert_eq!(game_state.crystal_score, 1);
-        assert_eq!(robot.role, Role::Explorer);
-        assert_eq!(robot.carrying, None);
-        assert_eq!(robot.resource_coords, None);
+    if let Some(path) = &cli.replay {
+        run_replay_mode(path);
+        return Ok(());
     }
 
-    #[test]
-    fn test_robot_discovers_map() {
-        let (mut ctx, mut game_state) = create_test_context();
-        let mut robot = Robot {
-            x: game_state.base_position.0,
-            y: game_state.base_position.1,
-            role: Role::Explorer,
-            resource_coords: None,
-            carrying: None,
-            speed: Robot::default_speed(),
-            move_counter: 0,
-        };
-
-        game_state.update_robot(&mut robot);
+    let mut recording = None;
+    let mut simulation = match &cli.resume {
+        Some(path) => {
+            let mut simulation = or_exit(snapshot::load(path));
+            if let Some(max_ticks) = cli.max_ticks {
+                simulation.end.max_ticks = Some(max_ticks);
+            }
+            println!(
+                "Reprise du snapshot {} au tick {}",
+                path.display(),
+                simulation.tick
+            );
+            simulation
+        }
+        None => {
+            let mut config = or_exit(cli.simulation_config());
+            println!("Seed: {}", config.seed);
+            if cli.headless {
+                config.end.max_ticks = config.end.max_ticks.or(Some(DEFAULT_HEADLESS_MAX_TICKS));
+            }
+            let simulation = or_exit(Simulation::new(&config));
+            if let Some(path) = &cli.record {
+                recording = Some(Recording {
+                    recorder: Recorder::new(
+                        &config,
+                        &simulation,
+                        replay::DEFAULT_CHECKPOINT_INTERVAL,
+                    ),
+                    path: path.clone(),
+                });
+            }
+            simulation
+        }
+    };
+
+    if let Some(path) = &cli.export_map {
+        or_exit(fs::write(
+            path,
+            ascii_map::to_ascii(&simulation.map, &simulation.terrain),
+        ));
+        println!("Carte exportée dans {}", path.display());
+    }
 
-        assert!(game_state.discovered[robot.y][robot.x]);
+    if cli.headless {
+        simulation.end.max_ticks = simulation
+            .end
+            .max_ticks
+            .or(Some(DEFAULT_HEADLESS_MAX_TICKS));
+        let capture = cli
+            .capture
+            .as_ref()
+            .map(|path| or_exit(Capture::new(path, cli.capture_every, cli.pixels_per_cell)));
+        let simulation = run_headless(simulation, recording, capture);
+        if let Some(path) = &cli.png {
+            or_exit(render::save_png(&simulation, path, cli.pixels_per_cell));
+            println!("Image écrite dans {}", path.display());
+        }
+        if let Some(path) = &cli.flow_png {
+            or_exit(render::save_flow_field_png(
+                &simulation,
+                path,
+                cli.pixels_per_cell,
+            ));
+            println!("Champ de distances écrit dans {}", path.display());
+        }
+        if let Some(path) = &cli.belief_png {
+            if cli.belief_robot >= simulation.robots.len() {
+                eprintln!(
+                    "Erreur: pas de robot {} ({} robots)",
+                    cli.belief_robot,
+                    simulation.robots.len()
+                );
+                process::exit(1);
+            }
+            or_exit(render::save_belief_png(
+                &simulation,
+                cli.belief_robot,
+                path,
+                cli.pixels_per_cell,
+            ));
+            println!(
+                "Carte du robot {} écrite dans {}",
+                cli.belief_robot,
+                path.display()
+            );
+        }
+        return Ok(());
     }
-}
 
-fn main() -> GameResult {
-    let seed = rand::thread_rng().gen();
+    // Fenêtre cachée le temps de connaître l'écran : une carte de 1000x1000 cases ne tient
+    // pas à 20 pixels par case, les cases rapetissent alors pour que toute la carte soit visible
     let cb = ggez::ContextBuilder::new("Rust Game", "ggez")
         .window_setup(conf::WindowSetup::default().title("Création de la map"))
-        .window_mode(conf::WindowMode::default().dimensions(800.0, 600.0));
+        .window_mode(conf::WindowMode::default().visible(false));
     let (mut ctx, event_loop) = cb.build()?;
-    let state = GameState::new(&mut ctx, seed)?;
+    let screen = graphics::window(&ctx)
+        .current_monitor()
+        .map(|monitor| {
+            let size = monitor.size();
+            (
+                size.width as f32 * SCREEN_SHARE,
+                size.height as f32 * SCREEN_SHARE,
+            )
+        })
+        .unwrap_or(FALLBACK_SCREEN);
+    let cell_size = render::fit_cell_size(
+        simulation.map_width,
+        simulation.map_height,
+        CELL_SIZE,
+        screen,
+    );
+    let width = simulation.map_width as f32 * cell_size;
+    let height = simulation.map_height as f32 * cell_size;
+    graphics::set_mode(
+        &mut ctx,
+        conf::WindowMode::default().dimensions(width, height),
+    )?;
+    graphics::set_screen_coordinates(&mut ctx, graphics::Rect::new(0.0, 0.0, width, height))?;
+    let state = GameState::new(simulation, cell_size, cli.snapshot_dir, recording);
     event::run(ctx, event_loop, state)
 }
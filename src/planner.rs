@@ -0,0 +1,470 @@
+use crate::simulation::{cuts_corner, Cell, Movement, Simulation};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+type Position = (usize, usize);
+// Priorité d'une case dans D* Lite : coût estimé du chemin qui y passe, puis coût jusqu'au but
+type Key = (usize, usize);
+
+const UNREACHED: usize = usize::MAX;
+
+// Coût jusqu'au but d'une case, et le même recalculé à partir de ses voisines. Une case dont
+// les deux diffèrent attend dans `open_set`
+#[derive(Clone, Copy, Debug)]
+struct Costs {
+    cost: usize,
+    lookahead: usize,
+}
+
+const UNREACHED_COSTS: Costs = Costs {
+    cost: UNREACHED,
+    lookahead: UNREACHED,
+};
+
+// Planification incrémentale façon D* Lite. La recherche part du but et garde ses coûts d'un
+// appel à l'autre : quand le robot avance ou que des cases changent, seules les cases touchées
+// sont reprises. Elle repart de zéro quand le but change
+#[derive(Clone, Debug, Default)]
+pub struct Planner {
+    goal: Option<Position>,
+    avoid_fog: bool,
+    width: usize,
+    // Départ de la recherche précédente, et somme des heuristiques entre les départs
+    // successifs : les priorités déjà dans `open_set` restent des minorants
+    last_start: Position,
+    offset: usize,
+    costs: Vec<Costs>,
+    // Recherche à laquelle appartiennent les coûts de chaque case, les autres valent
+    // UNREACHED_COSTS : repartir de zéro ne demande pas de vider `costs`
+    searches: Vec<u32>,
+    search: u32,
+    open_set: BinaryHeap<Reverse<(Key, Position)>>,
+    // Cases changées depuis la dernière recherche
+    changed: Vec<Position>,
+}
+
+// Avec une carte `known`, seules les cases qu'elle connaît sont franchissables
+fn passable(simulation: &Simulation, known: Option<&[Vec<bool>]>, (x, y): Position) -> bool {
+    simulation.map[y][x] != Cell::Obstacle && known.is_none_or(|known| known[y][x])
+}
+
+// Case atteinte par un pas de `position` dans la direction `delta`, et son coût
+fn step(
+    simulation: &Simulation,
+    known: Option<&[Vec<bool>]>,
+    position: Position,
+    delta: (isize, isize),
+) -> Option<(Position, usize)> {
+    let next = simulation.topology.neighbour(
+        position,
+        delta,
+        simulation.map_width,
+        simulation.map_height,
+    )?;
+    if !passable(simulation, known, next)
+        || cuts_corner(&simulation.map, simulation.topology, position, delta)
+    {
+        return None;
+    }
+    Some((
+        next,
+        simulation.terrain[next.1][next.0].cost() * Movement::step_cost(delta),
+    ))
+}
+
+// Voisines d'une case, dans toutes les directions permises
+fn neighbours<'a>(
+    simulation: &'a Simulation,
+    directions: &'static [(isize, isize)],
+    position: Position,
+) -> impl Iterator<Item = Position> + 'a {
+    directions.iter().filter_map(move |&delta| {
+        simulation
+            .topology
+            .neighbour(position, delta, simulation.map_width, simulation.map_height)
+    })
+}
+
+impl Planner {
+    // Une case a changé : franchissable, infranchissable ou entrée dans la carte du robot
+    pub fn mark_changed(&mut self, position: Position) {
+        if self.goal.is_some() {
+            self.changed.push(position);
+        }
+    }
+
+    // Chemin le moins coûteux, départ et arrivée inclus. Parmi les chemins de même coût, il
+    // prend toujours le premier dans l'ordre des directions : le résultat ne dépend pas des
+    // recherches précédentes, un snapshot rechargé donne les mêmes chemins
+    pub fn find_path(
+        &mut self,
+        simulation: &Simulation,
+        start: Position,
+        goal: Position,
+        known: Option<&[Vec<bool>]>,
+    ) -> Option<Vec<Position>> {
+        let cells = simulation.map_width * simulation.map_height;
+        if self.goal != Some(goal) || self.avoid_fog != known.is_some() || self.costs.len() != cells
+        {
+            self.reset(simulation, start, goal, known.is_some());
+        } else {
+            self.offset += simulation.heuristic(self.last_start, start);
+            self.last_start = start;
+            for changed in std::mem::take(&mut self.changed) {
+                // Les arêtes qui entrent dans la case et les diagonales qui la frôlent
+                self.update(simulation, known, changed);
+                for position in neighbours(simulation, Movement::Eight.directions(), changed) {
+                    self.update(simulation, known, position);
+                }
+            }
+        }
+        self.compute(simulation, known, start);
+        if self.costs(start).cost == UNREACHED {
+            return None;
+        }
+        if let Some(path) = self.walk(simulation, known, start, goal) {
+            return Some(path);
+        }
+        // Une case a changé sans passer par mark_changed : la recherche repart de zéro
+        self.reset(simulation, start, goal, known.is_some());
+        self.compute(simulation, known, start);
+        self.walk(simulation, known, start, goal)
+    }
+
+    // Descend les coûts jusqu'au but. None si un pas ne rapproche pas du but, les coûts ne
+    // correspondent alors plus à la carte
+    fn walk(
+        &self,
+        simulation: &Simulation,
+        known: Option<&[Vec<bool>]>,
+        start: Position,
+        goal: Position,
+    ) -> Option<Vec<Position>> {
+        let mut current = start;
+        let mut path = vec![start];
+        while current != goal {
+            let (_, next) = simulation
+                .movement
+                .directions()
+                .iter()
+                .filter_map(|&delta| {
+                    let (next, step_cost) = step(simulation, known, current, delta)?;
+                    let cost = self.costs(next).cost;
+                    (cost != UNREACHED).then(|| (step_cost + cost, next))
+                })
+                .enumerate()
+                .min_by_key(|&(order, (cost, _))| (cost, order))
+                .map(|(_, best)| best)?;
+            if self.costs(next).cost >= self.costs(current).cost {
+                return None;
+            }
+            path.push(next);
+            current = next;
+        }
+        Some(path)
+    }
+
+    fn reset(&mut self, simulation: &Simulation, start: Position, goal: Position, avoid_fog: bool) {
+        let cells = simulation.map_width * simulation.map_height;
+        if self.costs.len() != cells {
+            self.costs = vec![UNREACHED_COSTS; cells];
+            self.searches = vec![0; cells];
+        }
+        self.search = self.search.wrapping_add(1);
+        if self.search == 0 {
+            self.searches.fill(0);
+            self.search = 1;
+        }
+        self.goal = Some(goal);
+        self.avoid_fog = avoid_fog;
+        self.width = simulation.map_width;
+        self.last_start = start;
+        self.offset = 0;
+        self.open_set.clear();
+        self.changed.clear();
+        self.costs_mut(goal).lookahead = 0;
+        let key = self.key(simulation, start, goal);
+        self.open_set.push(Reverse((key, goal)));
+    }
+
+    fn costs(&self, (x, y): Position) -> Costs {
+        let index = y * self.width + x;
+        if self.searches[index] == self.search {
+            self.costs[index]
+        } else {
+            UNREACHED_COSTS
+        }
+    }
+
+    fn costs_mut(&mut self, (x, y): Position) -> &mut Costs {
+        let index = y * self.width + x;
+        if self.searches[index] != self.search {
+            self.searches[index] = self.search;
+            self.costs[index] = UNREACHED_COSTS;
+        }
+        &mut self.costs[index]
+    }
+
+    fn key(&self, simulation: &Simulation, start: Position, position: Position) -> Key {
+        let costs = self.costs(position);
+        let best = costs.cost.min(costs.lookahead);
+        (
+            best.saturating_add(simulation.heuristic(start, position) + self.offset),
+            best,
+        )
+    }
+
+    // Recalcule le coût d'une case à partir de ses voisines et la remet en attente si elle
+    // n'est plus à jour
+    fn update(&mut self, simulation: &Simulation, known: Option<&[Vec<bool>]>, position: Position) {
+        if Some(position) == self.goal {
+            return;
+        }
+        let lookahead = simulation
+            .movement
+            .directions()
+            .iter()
+            .filter_map(|&delta| {
+                let (next, step_cost) = step(simulation, known, position, delta)?;
+                let cost = self.costs(next).cost;
+                (cost != UNREACHED).then(|| step_cost + cost)
+            })
+            .min()
+            .unwrap_or(UNREACHED);
+        let costs = self.costs_mut(position);
+        costs.lookahead = lookahead;
+        if costs.cost != lookahead {
+            let key = self.key(simulation, self.last_start, position);
+            self.open_set.push(Reverse((key, position)));
+        }
+    }
+
+    // Traite les cases en attente jusqu'à ce que le départ soit à jour. Les cases à égalité
+    // avec le départ sont traitées aussi : tous les plus courts chemins ont alors leur coût
+    // exact, c'est ce qui rend le chemin choisi indépendant de l'historique
+    fn compute(&mut self, simulation: &Simulation, known: Option<&[Vec<bool>]>, start: Position) {
+        while let Some(&Reverse((key, position))) = self.open_set.peek() {
+            let start_costs = self.costs(start);
+            if key > self.key(simulation, start, start) && start_costs.cost == start_costs.lookahead
+            {
+                break;
+            }
+            self.open_set.pop();
+            let costs = self.costs(position);
+            if costs.cost == costs.lookahead {
+                continue;
+            }
+            let current_key = self.key(simulation, start, position);
+            if key != current_key {
+                // Une priorité calculée avant que le robot n'avance est trop basse ; une plus
+                // haute appartient à une entrée déjà remplacée
+                if key < current_key {
+                    self.open_set.push(Reverse((current_key, position)));
+                }
+                continue;
+            }
+            if costs.cost > costs.lookahead {
+                self.costs_mut(position).cost = costs.lookahead;
+            } else {
+                self.costs_mut(position).cost = UNREACHED;
+                self.update(simulation, known, position);
+            }
+            for neighbour in neighbours(simulation, simulation.movement.directions(), position) {
+                self.update(simulation, known, neighbour);
+            }
+        }
+    }
+}
+
+// Un planificateur par robot, rangé comme `Simulation::robots`. C'est un cache reconstruit à
+// la demande : il n'est pas sérialisé
+#[derive(Clone, Debug, Default)]
+pub struct Planners {
+    robots: Vec<Planner>,
+}
+
+impl Planners {
+    pub fn robot_mut(&mut self, robot: usize) -> &mut Planner {
+        if self.robots.len() <= robot {
+            self.robots.resize_with(robot + 1, Planner::default);
+        }
+        &mut self.robots[robot]
+    }
+
+    // Les prochaines recherches repartent de zéro, en gardant la place déjà allouée
+    pub fn forget(&mut self) {
+        for planner in &mut self.robots {
+            planner.goal = None;
+        }
+    }
+
+    // Une case rejoint la carte de la base : seuls les robots qui évitent le brouillard sont
+    // concernés
+    pub fn reveal(&mut self, position: Position) {
+        for planner in self.robots.iter_mut().filter(|planner| planner.avoid_fog) {
+            planner.mark_changed(position);
+        }
+    }
+
+    // Une case rejoint la carte du robot `robot`
+    pub fn reveal_to_robot(&mut self, robot: usize, position: Position) {
+        if let Some(planner) = self
+            .robots
+            .get_mut(robot)
+            .filter(|planner| planner.avoid_fog)
+        {
+            planner.mark_changed(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+    use crate::simulation::{Terrain, Topology};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    fn open_simulation(width: usize, height: usize, topology: Topology) -> Simulation {
+        let config = SimulationConfig {
+            map_width: width,
+            map_height: height,
+            topology,
+            ..SimulationConfig::default()
+        };
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        simulation.map = vec![vec![Cell::Empty; width]; height];
+        simulation.terrain = vec![vec![Terrain::Plain; width]; height];
+        simulation
+    }
+
+    fn path_cost(simulation: &Simulation, path: &[Position]) -> usize {
+        path.windows(2)
+            .map(|pair| {
+                let delta = simulation
+                    .movement
+                    .directions()
+                    .iter()
+                    .copied()
+                    .find(|&delta| {
+                        simulation.topology.neighbour(
+                            pair[0],
+                            delta,
+                            simulation.map_width,
+                            simulation.map_height,
+                        ) == Some(pair[1])
+                    })
+                    .unwrap();
+                step(simulation, None, pair[0], delta).unwrap().1
+            })
+            .sum()
+    }
+
+    fn a_star_cost(
+        simulation: &Simulation,
+        start: Position,
+        goal: Position,
+        known: Option<&[Vec<bool>]>,
+    ) -> Option<usize> {
+        let path = simulation.a_star_pathfinding(start, goal, known)?;
+        Some(path_cost(simulation, &path))
+    }
+
+    #[test]
+    fn test_path_matches_a_star_cost() {
+        let mut simulation = open_simulation(30, 20, Topology::Bounded);
+        for y in 0..15 {
+            simulation.map[y][10] = Cell::Obstacle;
+        }
+        simulation.terrain[17][5] = Terrain::Mud;
+        let mut planner = Planner::default();
+        let path = planner
+            .find_path(&simulation, (2, 2), (25, 3), None)
+            .unwrap();
+        assert_eq!((path[0], path[path.len() - 1]), ((2, 2), (25, 3)));
+        assert_eq!(
+            Some(path_cost(&simulation, &path)),
+            a_star_cost(&simulation, (2, 2), (25, 3), None)
+        );
+        assert!(!path.contains(&(5, 17)));
+    }
+
+    #[test]
+    fn test_unreachable_goal_until_revealed() {
+        let simulation = open_simulation(20, 10, Topology::Bounded);
+        let mut known = vec![vec![false; 20]; 10];
+        known[5][..10].fill(true);
+        let mut planner = Planner::default();
+        assert_eq!(
+            planner.find_path(&simulation, (0, 5), (15, 5), Some(&known)),
+            None
+        );
+
+        known[5][10..=15].fill(true);
+        for x in 10..=15 {
+            planner.mark_changed((x, 5));
+        }
+        let path = planner
+            .find_path(&simulation, (0, 5), (15, 5), Some(&known))
+            .unwrap();
+        assert_eq!(path.len(), 16);
+    }
+
+    // Le robot avance pendant que des obstacles apparaissent et que le brouillard se lève :
+    // la recherche reprise donne le même chemin qu'une recherche repartie de zéro
+    #[test]
+    fn test_incremental_search_matches_fresh_search() {
+        for (seed, topology, movement) in [
+            (1, Topology::Bounded, Movement::Four),
+            (2, Topology::Torus, Movement::Four),
+            (3, Topology::Bounded, Movement::Eight),
+            (4, Topology::Torus, Movement::Eight),
+        ] {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut simulation = open_simulation(24, 18, topology);
+            simulation.movement = movement;
+            for row in simulation.terrain.iter_mut() {
+                for terrain in row.iter_mut() {
+                    *terrain = [Terrain::Road, Terrain::Plain, Terrain::Mud][rng.gen_range(0..3)];
+                }
+            }
+            let mut known = vec![vec![false; 24]; 18];
+            for row in known.iter_mut() {
+                for cell in row.iter_mut() {
+                    *cell = rng.gen_bool(0.7);
+                }
+            }
+            let goal = (20, 14);
+            known[goal.1][goal.0] = true;
+            let mut start = (1, 1);
+            known[start.1][start.0] = true;
+            let mut planner = Planner::default();
+            for _ in 0..60 {
+                for _ in 0..4 {
+                    let position = (rng.gen_range(0..24), rng.gen_range(0..18));
+                    if position == start || position == goal {
+                        continue;
+                    }
+                    if rng.gen_bool(0.3) {
+                        simulation.map[position.1][position.0] = Cell::Obstacle;
+                    } else {
+                        known[position.1][position.0] = true;
+                    }
+                    planner.mark_changed(position);
+                }
+                let path = planner.find_path(&simulation, start, goal, Some(&known));
+                let fresh = Planner::default().find_path(&simulation, start, goal, Some(&known));
+                assert_eq!(path, fresh);
+                assert_eq!(
+                    path.as_ref().map(|path| path_cost(&simulation, path)),
+                    a_star_cost(&simulation, start, goal, Some(&known))
+                );
+                if let Some(path) = path.filter(|path| path.len() > 1) {
+                    start = path[1];
+                }
+            }
+        }
+    }
+}
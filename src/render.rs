@@ -0,0 +1,333 @@
+use crate::simulation::{Cell, Robot, Role, Simulation, Terrain};
+use image::{ImageFormat, ImageResult, Rgb, RgbImage};
+use std::path::Path;
+
+pub const DEFAULT_PIXELS_PER_CELL: u32 = 8;
+
+// Palette partagée avec la fenêtre ggez
+pub fn cell_color(cell: &Cell) -> [u8; 3] {
+    match cell {
+        Cell::Obstacle => [128, 128, 128],
+        Cell::Energy => [255, 255, 0],
+        Cell::Crystal => [128, 0, 128],
+        Cell::Base => [255, 0, 0],
+        Cell::ReservedEnergy => [255, 255, 128],
+        Cell::ReservedCrystal => [191, 0, 191],
+        Cell::Empty => [0, 204, 0],
+    }
+}
+
+pub fn terrain_color(terrain: Terrain) -> [u8; 3] {
+    match terrain {
+        Terrain::Road => [235, 225, 205],
+        Terrain::Plain => cell_color(&Cell::Empty),
+        Terrain::Sand => [220, 200, 100],
+        Terrain::Rock => [110, 90, 80],
+        Terrain::Mud => [90, 60, 30],
+    }
+}
+
+// Une case vide prend la couleur de son terrain
+pub fn ground_color(cell: &Cell, terrain: Terrain) -> [u8; 3] {
+    match cell {
+        Cell::Empty => terrain_color(terrain),
+        _ => cell_color(cell),
+    }
+}
+
+pub fn robot_color(role: &Role) -> [u8; 3] {
+    match role {
+        Role::Explorer => [0, 0, 255],
+        Role::Extractor => [255, 166, 0],
+    }
+}
+
+// Une épave à court de batterie est noire, quel que soit son rôle
+pub fn robot_state_color(robot: &Robot) -> [u8; 3] {
+    if robot.is_wreck() {
+        [20, 20, 20]
+    } else {
+        robot_color(&robot.role)
+    }
+}
+
+// Taille d'une case dans la fenêtre : `preferred`, réduite pour que toute la carte tienne
+// dans `max_size` pixels
+pub fn fit_cell_size(
+    map_width: usize,
+    map_height: usize,
+    preferred: f32,
+    (max_width, max_height): (f32, f32),
+) -> f32 {
+    preferred
+        .min(max_width / map_width as f32)
+        .min(max_height / map_height as f32)
+}
+
+// Dans l'image, une case non découverte reste visible mais assombrie,
+// pour pouvoir comparer la carte et le brouillard sur la même vue
+fn fogged([r, g, b]: [u8; 3]) -> [u8; 3] {
+    [r / 4, g / 4, b / 4]
+}
+
+// Case découverte mais qu'aucun explorateur ne voit en ce moment : son contenu a pu changer
+pub fn stale([r, g, b]: [u8; 3]) -> [u8; 3] {
+    [r / 2, g / 2, b / 2]
+}
+
+// Dégradé du champ de distances : bleu près de la base, rouge au plus loin
+pub fn flow_color(distance: usize, max_distance: usize) -> [u8; 3] {
+    let far = (distance * 255 / max_distance.max(1)) as u8;
+    [far, 0, 255 - far]
+}
+
+fn scaled_image(colors: &[Vec<[u8; 3]>], pixels_per_cell: u32) -> RgbImage {
+    RgbImage::from_fn(
+        colors[0].len() as u32 * pixels_per_cell,
+        colors.len() as u32 * pixels_per_cell,
+        |x, y| Rgb(colors[(y / pixels_per_cell) as usize][(x / pixels_per_cell) as usize]),
+    )
+}
+
+pub fn render_image(simulation: &Simulation, pixels_per_cell: u32) -> RgbImage {
+    render_known(
+        simulation,
+        &simulation.discovered,
+        &simulation.robots,
+        pixels_per_cell,
+    )
+}
+
+// Ce que croit savoir un robot : le brouillard suit sa propre carte
+pub fn render_belief(simulation: &Simulation, robot: usize, pixels_per_cell: u32) -> RgbImage {
+    render_known(
+        simulation,
+        simulation.knowledge(robot),
+        std::slice::from_ref(&simulation.robots[robot]),
+        pixels_per_cell,
+    )
+}
+
+fn render_known(
+    simulation: &Simulation,
+    known: &[Vec<bool>],
+    robots: &[Robot],
+    pixels_per_cell: u32,
+) -> RgbImage {
+    let mut colors: Vec<Vec<[u8; 3]>> = (0..simulation.map_height)
+        .map(|y| {
+            (0..simulation.map_width)
+                .map(|x| {
+                    let color = ground_color(&simulation.map[y][x], simulation.terrain[y][x]);
+                    if !known[y][x] {
+                        fogged(color)
+                    } else if simulation.is_visible((x, y)) {
+                        color
+                    } else {
+                        stale(color)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    for robot in robots {
+        colors[robot.y][robot.x] = robot_state_color(robot);
+    }
+    scaled_image(&colors, pixels_per_cell)
+}
+
+// Image de debug du champ de distances vers la base, les cases hors du champ restent noires
+pub fn render_flow_field(simulation: &Simulation, pixels_per_cell: u32) -> RgbImage {
+    let field = simulation.flow_field();
+    let max_distance = field.and_then(|field| field.max_distance()).unwrap_or(0);
+    let colors: Vec<Vec<[u8; 3]>> = (0..simulation.map_height)
+        .map(|y| {
+            (0..simulation.map_width)
+                .map(|x| match field.and_then(|field| field.distance((x, y))) {
+                    Some(distance) => flow_color(distance, max_distance),
+                    None => [0, 0, 0],
+                })
+                .collect()
+        })
+        .collect();
+    scaled_image(&colors, pixels_per_cell)
+}
+
+// Chiffres 3x5 pour le score, un bit par pixel, bit de poids fort à gauche
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const TEXT_COLOR: [u8; 3] = [255, 255, 255];
+
+fn fill_rect(image: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, Rgb(color));
+        }
+    }
+}
+
+// Renvoie l'abscisse qui suit le nombre
+fn draw_number(image: &mut RgbImage, mut x: u32, y: u32, scale: u32, value: u32) -> u32 {
+    for digit in value.to_string().bytes() {
+        for (row, bits) in DIGIT_GLYPHS[(digit - b'0') as usize].iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    let (px, py) = (x + column * scale, y + row as u32 * scale);
+                    fill_rect(image, px, py, scale, scale, TEXT_COLOR);
+                }
+            }
+        }
+        x += 4 * scale;
+    }
+    x
+}
+
+// Image de la carte sous un bandeau affichant les scores cristaux et énergies
+pub fn render_frame(simulation: &Simulation, pixels_per_cell: u32) -> RgbImage {
+    let map = render_image(simulation, pixels_per_cell);
+    let scale = (pixels_per_cell / 4).max(1);
+    let band_height = 7 * scale;
+    let mut frame = RgbImage::new(map.width(), map.height() + band_height);
+    image::imageops::replace(&mut frame, &map, 0, band_height);
+
+    let mut x = scale;
+    for (cell, score) in [
+        (Cell::Crystal, simulation.crystal_score),
+        (Cell::Energy, simulation.energy_score),
+    ] {
+        fill_rect(
+            &mut frame,
+            x,
+            scale,
+            5 * scale,
+            5 * scale,
+            cell_color(&cell),
+        );
+        x = draw_number(&mut frame, x + 6 * scale, scale, scale, score) + 3 * scale;
+    }
+    frame
+}
+
+pub fn save_png(simulation: &Simulation, path: &Path, pixels_per_cell: u32) -> ImageResult<()> {
+    render_image(simulation, pixels_per_cell).save_with_format(path, ImageFormat::Png)
+}
+
+pub fn save_belief_png(
+    simulation: &Simulation,
+    robot: usize,
+    path: &Path,
+    pixels_per_cell: u32,
+) -> ImageResult<()> {
+    render_belief(simulation, robot, pixels_per_cell).save_with_format(path, ImageFormat::Png)
+}
+
+pub fn save_flow_field_png(
+    simulation: &Simulation,
+    path: &Path,
+    pixels_per_cell: u32,
+) -> ImageResult<()> {
+    render_flow_field(simulation, pixels_per_cell).save_with_format(path, ImageFormat::Png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+
+    fn layout_simulation() -> Simulation {
+        let mut config = SimulationConfig::default();
+        config
+            .set_map_layout("#####\n#B.C#\n#####\n".to_string())
+            .unwrap();
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    #[test]
+    fn test_image_size_follows_pixels_per_cell() {
+        let image = render_image(&layout_simulation(), 3);
+        assert_eq!(image.dimensions(), (15, 9));
+    }
+
+    #[test]
+    fn test_image_shows_cells_fog_and_robots() {
+        let mut simulation = layout_simulation();
+        simulation.discovered[1][2] = true;
+        simulation.visible[1][2] = true;
+        simulation.discovered[0][2] = true;
+        let image = render_image(&simulation, 2);
+
+        // Robots sur la base, case vide vue, mur découvert hors de vue, cristal dans le brouillard
+        assert_eq!(image.get_pixel(3, 3).0, robot_color(&Role::Explorer));
+        assert_eq!(image.get_pixel(4, 2).0, cell_color(&Cell::Empty));
+        assert_eq!(image.get_pixel(4, 0).0, stale(cell_color(&Cell::Obstacle)));
+        assert_eq!(image.get_pixel(7, 3).0, fogged(cell_color(&Cell::Crystal)));
+
+        // Tous les robots sur la base sont à court de batterie
+        for robot in &mut simulation.robots {
+            robot.charge = Some(0);
+        }
+        let image = render_image(&simulation, 2);
+        assert_eq!(
+            image.get_pixel(3, 3).0,
+            robot_state_color(&simulation.robots[0])
+        );
+        assert_ne!(image.get_pixel(3, 3).0, robot_color(&Role::Explorer));
+    }
+
+    #[test]
+    fn test_frame_has_score_band() {
+        let mut simulation = layout_simulation();
+        simulation.crystal_score = 7;
+        let frame = render_frame(&simulation, 4);
+        assert_eq!(frame.dimensions(), (20, 19));
+        assert_eq!(frame.get_pixel(1, 1).0, cell_color(&Cell::Crystal));
+        // Premier rang du 7 plein, puis la carte sous le bandeau
+        assert_eq!(frame.get_pixel(9, 1).0, TEXT_COLOR);
+        assert_eq!(
+            frame.get_pixel(0, 7).0,
+            render_image(&simulation, 4).get_pixel(0, 0).0
+        );
+    }
+
+    #[test]
+    fn test_flow_field_image() {
+        let mut simulation = layout_simulation();
+        while !simulation.discovered[1][3] {
+            simulation.step();
+        }
+        let image = render_flow_field(&simulation, 1);
+        // Base au plus près, cristal au plus loin, obstacles hors du champ
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(3, 1).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_large_map_fits_on_screen() {
+        assert_eq!(fit_cell_size(40, 30, 20.0, (1800.0, 900.0)), 20.0);
+        assert_eq!(fit_cell_size(100, 50, 20.0, (1800.0, 900.0)), 18.0);
+        assert_eq!(fit_cell_size(1000, 1000, 20.0, (1800.0, 900.0)), 0.9);
+    }
+
+    #[test]
+    fn test_save_png_round_trip() {
+        let simulation = layout_simulation();
+        let path = std::env::temp_dir().join(format!("rust_game_{}.png", std::process::id()));
+        save_png(&simulation, &path, 4).unwrap();
+        let loaded = image::open(&path).unwrap().to_rgb8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, render_image(&simulation, 4));
+    }
+}
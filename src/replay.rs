@@ -0,0 +1,335 @@
+use crate::behaviour::Strategies;
+use crate::config::{ConfigError, SimulationConfig};
+use crate::simulation::{RobotAction, Simulation};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Version du format du fichier, à incrémenter seulement quand un replay existant ne peut plus
+// être lu. Un changement des règles n'en change pas : le replay d'une version du jeu qui jouait
+// autrement diverge au premier tick concerné, ce que --replay sert justement à montrer
+pub const REPLAY_VERSION: u32 = 13;
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 100;
+
+// Enregistrement d'une partie : la configuration suffit à la rejouer,
+// les actions et les empreintes servent à vérifier qu'elle se déroule pareil
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub config: SimulationConfig,
+    pub checkpoint_interval: u64,
+    // Une chaîne par tick, un caractère par robot (voir action_char)
+    pub ticks: Vec<String>,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub tick: u64,
+    pub hash: u64,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    Version(u32),
+    // Stratégie enregistrée par le programme qui a produit le replay, absente du fichier
+    UnknownStrategy(String),
+    Config(ConfigError),
+    Divergence { tick: u64, detail: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "accès au replay impossible: {}", err),
+            ReplayError::Format(err) => write!(f, "replay illisible: {}", err),
+            ReplayError::Version(version) => write!(
+                f,
+                "replay en version {} alors que cette version du jeu lit la version {}",
+                version, REPLAY_VERSION
+            ),
+            ReplayError::UnknownStrategy(name) => write!(
+                f,
+                "le replay utilise la stratégie '{}', qui n'est ni intégrée ni décrite par son \
+                 scénario : il faut l'enregistrer pour le vérifier",
+                name
+            ),
+            ReplayError::Config(err) => write!(f, "{}", err),
+            ReplayError::Divergence { tick, detail } => {
+                write!(f, "divergence au tick {}: {}", tick, detail)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// . attend, _ reste sur place, L/R/U/D déplacement, 7/9/1/3 diagonales comme sur un pavé numérique
+fn action_char(action: RobotAction) -> char {
+    match action {
+        RobotAction::Wait => '.',
+        RobotAction::Stay => '_',
+        RobotAction::Move(-1, 0) => 'L',
+        RobotAction::Move(1, 0) => 'R',
+        RobotAction::Move(0, -1) => 'U',
+        RobotAction::Move(0, 1) => 'D',
+        RobotAction::Move(-1, -1) => '7',
+        RobotAction::Move(1, -1) => '9',
+        RobotAction::Move(-1, 1) => '1',
+        RobotAction::Move(1, 1) => '3',
+        RobotAction::Move(_, _) => '?',
+    }
+}
+
+fn encode_actions(actions: &[RobotAction]) -> String {
+    actions.iter().copied().map(action_char).collect()
+}
+
+pub struct Recorder {
+    replay: Replay,
+}
+
+impl Recorder {
+    // À créer avant le premier tick de la simulation issue de `config`
+    pub fn new(config: &SimulationConfig, simulation: &Simulation, interval: u64) -> Recorder {
+        Recorder {
+            replay: Replay {
+                version: REPLAY_VERSION,
+                config: config.clone(),
+                checkpoint_interval: interval,
+                ticks: Vec::new(),
+                checkpoints: vec![Checkpoint {
+                    tick: simulation.tick,
+                    hash: simulation.state_hash(),
+                }],
+            },
+        }
+    }
+
+    // À appeler après chaque tick avec les actions renvoyées par step
+    pub fn record(&mut self, simulation: &Simulation, actions: &[RobotAction]) {
+        if actions.is_empty() {
+            return;
+        }
+        self.replay.ticks.push(encode_actions(actions));
+        if simulation
+            .tick
+            .is_multiple_of(self.replay.checkpoint_interval)
+            || simulation.game_over
+        {
+            self.replay.checkpoints.push(Checkpoint {
+                tick: simulation.tick,
+                hash: simulation.state_hash(),
+            });
+        }
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+impl Replay {
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let content = serde_json::to_string(self).map_err(ReplayError::Format)?;
+        fs::write(path, content).map_err(ReplayError::Io)
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        let content = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        let replay: Replay = serde_json::from_str(&content).map_err(ReplayError::Format)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::Version(replay.version));
+        }
+        Ok(replay)
+    }
+
+    // Rejoue la partie et renvoie le nombre de ticks vérifiés, ou le premier tick divergent
+    pub fn verify(&self) -> Result<u64, ReplayError> {
+        self.verify_with_strategies(&Strategies::default())
+    }
+
+    // Le replay ne garde que le nom des stratégies enregistrées : il faut les redonner
+    pub fn verify_with_strategies(&self, strategies: &Strategies) -> Result<u64, ReplayError> {
+        let config = SimulationConfig {
+            strategies: strategies.clone(),
+            ..self.config.clone()
+        };
+        if let Some(name) = config.unknown_strategy() {
+            return Err(ReplayError::UnknownStrategy(name.to_string()));
+        }
+        let mut simulation = Simulation::new(&config).map_err(ReplayError::Config)?;
+        simulation.verbose = false;
+        let mut checkpoints = self.checkpoints.iter().peekable();
+
+        self.check_hash(&simulation, &mut checkpoints)?;
+        for expected in &self.ticks {
+            let actions = simulation.step();
+            if actions.is_empty() {
+                return Err(ReplayError::Divergence {
+                    tick: simulation.tick + 1,
+                    detail: "la partie est déjà terminée".to_string(),
+                });
+            }
+
+            let replayed = encode_actions(&actions);
+            if replayed != *expected {
+                return Err(ReplayError::Divergence {
+                    tick: simulation.tick,
+                    detail: describe_action_mismatch(expected, &replayed),
+                });
+            }
+            self.check_hash(&simulation, &mut checkpoints)?;
+        }
+        Ok(simulation.tick)
+    }
+
+    fn check_hash<'a>(
+        &self,
+        simulation: &Simulation,
+        checkpoints: &mut std::iter::Peekable<impl Iterator<Item = &'a Checkpoint>>,
+    ) -> Result<(), ReplayError> {
+        if let Some(checkpoint) = checkpoints.next_if(|c| c.tick == simulation.tick) {
+            let hash = simulation.state_hash();
+            if hash != checkpoint.hash {
+                return Err(ReplayError::Divergence {
+                    tick: simulation.tick,
+                    detail: format!(
+                        "empreinte d'état {:016x} au lieu de {:016x}",
+                        hash, checkpoint.hash
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe_action_mismatch(expected: &str, replayed: &str) -> String {
+    if expected.chars().count() != replayed.chars().count() {
+        return format!(
+            "{} robots enregistrés, {} rejoués",
+            expected.chars().count(),
+            replayed.chars().count()
+        );
+    }
+    let (robot, (expected, replayed)) = expected
+        .chars()
+        .zip(replayed.chars())
+        .enumerate()
+        .find(|(_, (expected, replayed))| expected != replayed)
+        .expect("les actions diffèrent");
+    format!(
+        "robot {}: action '{}' enregistrée, '{}' rejouée",
+        robot, expected, replayed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EndConditions;
+
+    fn recorded_run(ticks: u64) -> Replay {
+        let config = SimulationConfig {
+            seed: 5,
+            end: EndConditions {
+                max_ticks: Some(ticks),
+                ..EndConditions::default()
+            },
+            ..SimulationConfig::default()
+        };
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        let mut recorder = Recorder::new(&config, &simulation, 50);
+        while !simulation.game_over {
+            let actions = simulation.step();
+            recorder.record(&simulation, &actions);
+        }
+        recorder.finish()
+    }
+
+    #[test]
+    fn test_replay_verifies_recorded_run() {
+        let replay = recorded_run(420);
+        assert_eq!(replay.ticks.len(), 420);
+        // tick 0, tous les 50 ticks et la fin de partie
+        assert_eq!(replay.checkpoints.len(), 10);
+        assert_eq!(replay.verify().unwrap(), 420);
+    }
+
+    #[test]
+    fn test_replay_reports_first_diverging_action() {
+        let mut replay = recorded_run(300);
+        let first = replay.ticks[122].remove(0);
+        let other = if first == 'L' { 'R' } else { 'L' };
+        replay.ticks[122].insert(0, other);
+        match replay.verify() {
+            Err(ReplayError::Divergence { tick, .. }) => assert_eq!(tick, 123),
+            other => panic!("divergence attendue, obtenu {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_replay_reports_hash_divergence() {
+        let mut replay = recorded_run(300);
+        replay.checkpoints[3].hash ^= 1;
+        match replay.verify() {
+            Err(ReplayError::Divergence { tick, .. }) => assert_eq!(tick, 150),
+            other => panic!("divergence attendue, obtenu {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_replay_with_registered_strategy_needs_it() {
+        let mut config = SimulationConfig {
+            seed: 5,
+            end: EndConditions {
+                max_ticks: Some(100),
+                ..EndConditions::default()
+            },
+            ..SimulationConfig::default()
+        };
+        config.robots[0].strategy = "marcheur".to_string();
+        config
+            .strategies
+            .register("marcheur", Box::new(crate::behaviour::RandomWalk));
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        let mut recorder = Recorder::new(&config, &simulation, 50);
+        while !simulation.game_over {
+            let actions = simulation.step();
+            recorder.record(&simulation, &actions);
+        }
+
+        // Le fichier ne garde que le nom de la stratégie
+        let content = serde_json::to_string(&recorder.finish()).unwrap();
+        let replay: Replay = serde_json::from_str(&content).unwrap();
+        match replay.verify() {
+            Err(ReplayError::UnknownStrategy(name)) => assert_eq!(name, "marcheur"),
+            other => panic!("stratégie inconnue attendue, obtenu {:?}", other),
+        }
+        assert_eq!(
+            replay.verify_with_strategies(&config.strategies).unwrap(),
+            100
+        );
+    }
+
+    #[test]
+    fn test_action_encoding() {
+        assert_eq!(
+            encode_actions(&[
+                RobotAction::Wait,
+                RobotAction::Stay,
+                RobotAction::Move(0, 1),
+                RobotAction::Move(-1, 0),
+                RobotAction::Move(1, -1),
+                RobotAction::Move(-1, 1),
+            ]),
+            "._DL91"
+        );
+    }
+}
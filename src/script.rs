@@ -0,0 +1,399 @@
+use crate::behaviour::{Command, WorldView};
+use crate::behaviour_tree::{self, Action};
+use crate::config::ConfigError;
+use crate::simulation::{Cell, Robot, Role, Topology};
+use rand::RngCore;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+type Position = (usize, usize);
+
+// Opérations Rhai qu'un script peut exécuter par tick avant d'être interrompu
+pub const OPERATION_BUDGET: u64 = 20_000;
+// Rayon autour du robot de la carte connue transmise au script
+pub const VIEW_RADIUS: isize = 8;
+
+// Cerveau de robot écrit en Rhai. Seul le source est sauvegardé dans les snapshots,
+// il est recompilé au chargement
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RobotScript {
+    source: String,
+    compiled: Arc<(Engine, AST)>,
+}
+
+impl fmt::Debug for RobotScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RobotScript")
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl PartialEq for RobotScript {
+    fn eq(&self, other: &RobotScript) -> bool {
+        self.source == other.source
+    }
+}
+
+impl TryFrom<String> for RobotScript {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<RobotScript, String> {
+        let engine = engine();
+        let ast = engine.compile(&source).map_err(|err| err.to_string())?;
+        Ok(RobotScript {
+            source,
+            compiled: Arc::new((engine, ast)),
+        })
+    }
+}
+
+impl From<RobotScript> for String {
+    fn from(script: RobotScript) -> String {
+        script.source
+    }
+}
+
+// Case visée par step(x, y) ou go_to(x, y)
+#[derive(Clone)]
+enum Target {
+    Step(i64, i64),
+    GoTo(i64, i64),
+}
+
+// Cases découvertes autour du robot, seule partie de la carte que le script peut lire
+#[derive(Clone)]
+struct KnownMap {
+    width: i64,
+    height: i64,
+    topology: Topology,
+    cells: HashMap<Position, &'static str>,
+}
+
+impl KnownMap {
+    fn new(robot: &Robot, world: &WorldView) -> KnownMap {
+        let simulation = world.simulation;
+        let mut cells = HashMap::new();
+        for dy in -VIEW_RADIUS..=VIEW_RADIUS {
+            for dx in -VIEW_RADIUS..=VIEW_RADIUS {
+                let Some((x, y)) = simulation.topology.neighbour(
+                    (robot.x, robot.y),
+                    (dx, dy),
+                    simulation.map_width,
+                    simulation.map_height,
+                ) else {
+                    continue;
+                };
+                if world.known[y][x] {
+                    cells.insert((x, y), cell_name(&simulation.map[y][x]));
+                }
+            }
+        }
+        KnownMap {
+            width: simulation.map_width as i64,
+            height: simulation.map_height as i64,
+            topology: simulation.topology,
+            cells,
+        }
+    }
+
+    fn cell(&mut self, x: i64, y: i64) -> ImmutableString {
+        position(x, y, self.width, self.height, self.topology)
+            .and_then(|position| self.cells.get(&position))
+            .copied()
+            .unwrap_or("unknown")
+            .into()
+    }
+}
+
+fn cell_name(cell: &Cell) -> &'static str {
+    match cell {
+        Cell::Empty => "empty",
+        Cell::Obstacle => "obstacle",
+        Cell::Energy => "energy",
+        Cell::Crystal => "crystal",
+        // Ressource déjà repérée par un autre explorateur
+        Cell::ReservedEnergy | Cell::ReservedCrystal => "reserved",
+        Cell::Base => "base",
+    }
+}
+
+// Sur un tore les coordonnées négatives ou trop grandes font le tour de la carte
+fn position(x: i64, y: i64, width: i64, height: i64, topology: Topology) -> Option<Position> {
+    let (x, y) = match topology {
+        Topology::Torus => (x.rem_euclid(width), y.rem_euclid(height)),
+        Topology::Bounded => (x, y),
+    };
+    ((0..width).contains(&x) && (0..height).contains(&y)).then_some((x as usize, y as usize))
+}
+
+// Moteur sans accès au système, avec des limites pour qu'un script ne puisse pas
+// bloquer la simulation
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(OPERATION_BUDGET)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1_000)
+        .set_max_array_size(1_000)
+        .set_max_map_size(100);
+    engine.disable_symbol("eval");
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine
+        .register_type_with_name::<KnownMap>("KnownMap")
+        .register_fn("cell", KnownMap::cell)
+        .register_get("width", |map: &mut KnownMap| map.width)
+        .register_get("height", |map: &mut KnownMap| map.height)
+        .register_type_with_name::<Target>("Target")
+        .register_fn("step", Target::Step)
+        .register_fn("go_to", Target::GoTo);
+    engine
+}
+
+fn coordinates((x, y): Position) -> Array {
+    vec![Dynamic::from(x as i64), Dynamic::from(y as i64)]
+}
+
+fn robot_map(robot: &Robot, world: &WorldView) -> Map {
+    let base_position = world.simulation.base_position;
+    let role = match robot.role {
+        Role::Explorer => "explorer",
+        Role::Extractor => "extractor",
+    };
+    let mut map = Map::new();
+    map.insert("x".into(), Dynamic::from(robot.x as i64));
+    map.insert("y".into(), Dynamic::from(robot.y as i64));
+    map.insert("role".into(), role.into());
+    map.insert("carrying".into(), robot.carrying.is_some().into());
+    map.insert(
+        "knows_resource".into(),
+        robot.resource_coords.is_some().into(),
+    );
+    map.insert(
+        "resource".into(),
+        robot
+            .resource_coords
+            .map_or(Dynamic::UNIT, |position| coordinates(position).into()),
+    );
+    map.insert("base".into(), coordinates(base_position).into());
+    map.insert(
+        "at_base".into(),
+        ((robot.x, robot.y) == base_position).into(),
+    );
+    map.insert("tick".into(), Dynamic::from(world.simulation.tick as i64));
+    map
+}
+
+impl RobotScript {
+    pub fn load(path: &Path) -> Result<RobotScript, ConfigError> {
+        let source = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        RobotScript::try_from(source).map_err(|err| {
+            ConfigError::Invalid(format!("script {} invalide: {}", path.display(), err))
+        })
+    }
+
+    // Exécute le script avec les constantes `robot` et `map`. Il renvoie le nom d'une action
+    // des arbres de comportement, step(x, y), go_to(x, y) ou rien pour attendre
+    pub fn decide(
+        &self,
+        robot: &Robot,
+        world: &WorldView,
+        rng: &mut dyn RngCore,
+    ) -> Result<Command, String> {
+        let (engine, ast) = &*self.compiled;
+        let mut scope = Scope::new();
+        scope.push_constant("robot", robot_map(robot, world));
+        scope.push_constant("map", KnownMap::new(robot, world));
+        let result = engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+            .map_err(|err| match *err {
+                EvalAltResult::ErrorTooManyOperations(_) => {
+                    format!("budget de {} opérations dépassé", OPERATION_BUDGET)
+                }
+                err => err.to_string(),
+            })?;
+        command(result, robot, world, rng)
+    }
+}
+
+fn command(
+    result: Dynamic,
+    robot: &Robot,
+    world: &WorldView,
+    rng: &mut dyn RngCore,
+) -> Result<Command, String> {
+    if result.is_unit() {
+        return Ok(Command::Stay);
+    }
+    if result.is_string() {
+        let name = result.into_string()?;
+        let action =
+            Action::from_name(&name).ok_or_else(|| format!("action '{}' inconnue", name))?;
+        return Ok(behaviour_tree::command(action, robot, world, rng).unwrap_or(Command::Stay));
+    }
+    let type_name = result.type_name();
+    let target = result
+        .try_cast::<Target>()
+        .ok_or_else(|| format!("le script doit renvoyer une action, pas {}", type_name))?;
+    let simulation = world.simulation;
+    let (x, y) = match target {
+        Target::Step(x, y) | Target::GoTo(x, y) => (x, y),
+    };
+    let target_position = position(
+        x,
+        y,
+        simulation.map_width as i64,
+        simulation.map_height as i64,
+        simulation.topology,
+    )
+    .ok_or_else(|| format!("case ({}, {}) hors de la carte", x, y))?;
+    Ok(match target {
+        Target::Step(..) => Command::Step(target_position),
+        Target::GoTo(..) => Command::GoTo {
+            target: target_position,
+            avoid_fog: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RobotConfig, SimulationConfig};
+    use crate::simulation::Simulation;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    fn script(source: &str) -> RobotScript {
+        RobotScript::try_from(source.to_string()).unwrap()
+    }
+
+    fn simulation(source: &str) -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            robots: vec![RobotConfig {
+                strategy: "script".to_string(),
+                ..RobotConfig::default()
+            }],
+            scripts: [("script".to_string(), script(source))].into(),
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    fn decide(source: &str, simulation: &Simulation) -> Result<Command, String> {
+        let world = WorldView {
+            simulation,
+            known: &simulation.discovered,
+            claimed_frontiers: &[],
+        };
+        script(source).decide(
+            &simulation.robots[0],
+            &world,
+            &mut ChaCha12Rng::seed_from_u64(0),
+        )
+    }
+
+    #[test]
+    fn test_script_returns_commands() {
+        let mut simulation = simulation("()");
+        let (x, y) = simulation.base_position;
+        assert_eq!(decide("()", &simulation), Ok(Command::Stay));
+        assert_eq!(decide(r#""scan""#, &simulation), Ok(Command::Scan));
+        // Sans ressource connue, le ramassage n'a rien à faire
+        assert_eq!(decide(r#""pick_up""#, &simulation), Ok(Command::Stay));
+        assert_eq!(
+            decide("step(robot.x + 1, robot.y)", &simulation),
+            Ok(Command::Step((x + 1, y)))
+        );
+        // La carte par défaut est un tore : les coordonnées font le tour
+        assert_eq!(
+            decide("go_to(-1, robot.base[1])", &simulation),
+            Ok(Command::GoTo {
+                target: (simulation.map_width - 1, y),
+                avoid_fog: false
+            })
+        );
+        simulation.topology = Topology::Bounded;
+        assert!(decide("go_to(-1, 0)", &simulation).is_err());
+        assert!(decide(r#""voler""#, &simulation).is_err());
+        assert!(decide("42", &simulation).is_err());
+    }
+
+    #[test]
+    fn test_script_reads_known_map() {
+        let mut simulation = simulation("()");
+        let (x, y) = simulation.base_position;
+        let source = "map.cell(robot.x, robot.y) + map.cell(robot.x + 1, robot.y)";
+        let cell = |simulation: &Simulation| {
+            let world = WorldView {
+                simulation,
+                known: &simulation.discovered,
+                claimed_frontiers: &[],
+            };
+            let mut scope = Scope::new();
+            scope.push_constant("robot", robot_map(&simulation.robots[0], &world));
+            scope.push_constant("map", KnownMap::new(&simulation.robots[0], &world));
+            engine()
+                .eval_with_scope::<String>(&mut scope, source)
+                .unwrap()
+        };
+        assert_eq!(cell(&simulation), "unknownunknown");
+        simulation.discovered[y][x] = true;
+        simulation.discovered[y][x + 1] = true;
+        simulation.map[y][x + 1] = Cell::ReservedCrystal;
+        assert_eq!(cell(&simulation), "basereserved");
+    }
+
+    #[test]
+    fn test_runaway_script_is_interrupted() {
+        let mut simulation = simulation("loop {}");
+        let position = simulation.base_position;
+        for _ in 0..4 {
+            simulation.step();
+        }
+        let robot = &simulation.robots[0];
+        assert_eq!((robot.x, robot.y), position);
+        assert_eq!(
+            robot.script_error.as_deref(),
+            Some("budget de 20000 opérations dépassé")
+        );
+    }
+
+    #[test]
+    fn test_invalid_script_is_rejected() {
+        assert!(RobotScript::try_from("if {".to_string()).is_err());
+        assert!(matches!(
+            RobotScript::load(Path::new("scripts/absent.rhai")),
+            Err(ConfigError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_example_script_collects_resources() {
+        let config = SimulationConfig::load(Path::new("scenarios/script.toml")).unwrap();
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        while !simulation.game_over {
+            simulation.step();
+        }
+        assert!(simulation.crystal_score + simulation.energy_score > 0);
+        assert!(simulation
+            .robots
+            .iter()
+            .all(|robot| robot.script_error.is_none()));
+
+        // Le script est sauvegardé par son source et recompilé au chargement
+        let json = serde_json::to_string(&simulation).unwrap();
+        let loaded: Simulation = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.scripts, simulation.scripts);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::field_of_view;
+use crate::simulation::{Cell, Simulation};
+use serde::{Deserialize, Serialize};
+
+type Position = (usize, usize);
+
+pub const DEFAULT_SENSOR: &str = "default";
+
+// Cases couvertes autour du robot, à au plus `radius` cases
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorShape {
+    #[default]
+    Square,
+    // Distance euclidienne
+    Circle,
+    // Distance de Manhattan
+    Diamond,
+}
+
+impl SensorShape {
+    fn contains(self, (dx, dy): (isize, isize), radius: isize) -> bool {
+        match self {
+            SensorShape::Square => true,
+            SensorShape::Circle => dx * dx + dy * dy <= radius * radius,
+            SensorShape::Diamond => dx.abs() + dy.abs() <= radius,
+        }
+    }
+}
+
+// Capteur d'un type de robot. Celui par défaut reprend l'ancien comportement : le carré
+// de 3x3 autour du robot, où rien ne peut être caché, et toute ressource vue est détectée
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sensor {
+    #[serde(default = "Sensor::default_radius")]
+    pub radius: usize,
+    #[serde(default)]
+    pub shape: SensorShape,
+    // Les obstacles cachent les cases derrière eux
+    #[serde(default = "Sensor::line_of_sight")]
+    pub line_of_sight: bool,
+    // Chance de repérer une ressource dans le champ, tirée à chaque tick
+    #[serde(default = "Sensor::certain")]
+    pub detection_probability: f64,
+}
+
+impl Default for Sensor {
+    fn default() -> Self {
+        Sensor {
+            radius: Sensor::default_radius(),
+            shape: SensorShape::default(),
+            line_of_sight: Sensor::line_of_sight(),
+            detection_probability: Sensor::certain(),
+        }
+    }
+}
+
+impl Sensor {
+    fn default_radius() -> usize {
+        1
+    }
+
+    fn line_of_sight() -> bool {
+        true
+    }
+
+    fn certain() -> f64 {
+        1.0
+    }
+
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.detection_probability) {
+            return Err(format!(
+                "la probabilité de détection du capteur '{}' doit être entre 0 et 1 (reçu {})",
+                name, self.detection_probability
+            ));
+        }
+        Ok(())
+    }
+
+    // Cases vues depuis `origin`, ligne par ligne
+    pub fn field(&self, simulation: &Simulation, origin: Position) -> Vec<Position> {
+        // Les bords d'une carte bornée arrêtent la vue comme des obstacles
+        let visible = self.line_of_sight.then(|| {
+            field_of_view::shadowcast(self.radius, |delta| {
+                cell(simulation, origin, delta)
+                    .is_none_or(|(x, y)| simulation.map[y][x] == Cell::Obstacle)
+            })
+        });
+        let radius = self.radius as isize;
+        (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|&delta| self.shape.contains(delta, radius))
+            .filter(|&(dx, dy)| {
+                visible
+                    .as_ref()
+                    .is_none_or(|visible| visible[(dy + radius) as usize][(dx + radius) as usize])
+            })
+            .filter_map(|delta| cell(simulation, origin, delta))
+            .collect()
+    }
+}
+
+fn cell(simulation: &Simulation, origin: Position, delta: (isize, isize)) -> Option<Position> {
+    simulation
+        .topology
+        .neighbour(origin, delta, simulation.map_width, simulation.map_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+
+    // Carte vide de 11x11, robot au centre
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig::default()).unwrap();
+        simulation.map = vec![vec![Cell::Empty; 11]; 11];
+        simulation.map_width = 11;
+        simulation.map_height = 11;
+        simulation
+    }
+
+    #[test]
+    fn test_default_sensor_sees_3x3() {
+        let simulation = simulation();
+        let field = Sensor::default().field(&simulation, (5, 5));
+        assert_eq!(field.len(), 9);
+        assert_eq!(field[0], (4, 4));
+        assert_eq!(field[8], (6, 6));
+    }
+
+    #[test]
+    fn test_sensor_shapes() {
+        let simulation = simulation();
+        let count = |shape| {
+            Sensor {
+                radius: 2,
+                shape,
+                ..Sensor::default()
+            }
+            .field(&simulation, (5, 5))
+            .len()
+        };
+        assert_eq!(count(SensorShape::Square), 25);
+        assert_eq!(count(SensorShape::Circle), 13);
+        assert_eq!(count(SensorShape::Diamond), 13);
+        let circle = Sensor {
+            radius: 3,
+            shape: SensorShape::Circle,
+            ..Sensor::default()
+        };
+        assert_eq!(circle.field(&simulation, (5, 5)).len(), 29);
+    }
+
+    #[test]
+    fn test_obstacles_block_line_of_sight() {
+        let mut simulation = simulation();
+        simulation.map[5][6] = Cell::Obstacle;
+        let sensor = Sensor {
+            radius: 3,
+            ..Sensor::default()
+        };
+        let field = sensor.field(&simulation, (5, 5));
+        // Le mur est vu, pas ce qu'il y a derrière
+        assert!(field.contains(&(6, 5)));
+        assert!(!field.contains(&(7, 5)));
+        assert!(!field.contains(&(8, 5)));
+        assert!(field.contains(&(8, 7)));
+        let blind = Sensor {
+            line_of_sight: false,
+            ..sensor
+        };
+        assert!(blind.field(&simulation, (5, 5)).contains(&(8, 5)));
+    }
+
+    #[test]
+    fn test_detection_probability_is_checked() {
+        let sensor = Sensor {
+            detection_probability: 1.5,
+            ..Sensor::default()
+        };
+        assert!(sensor.validate("radar").is_err());
+        assert!(Sensor::default().validate("radar").is_ok());
+    }
+}
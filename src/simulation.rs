@@ -0,0 +1,2608 @@
+use crate::ascii_map::{self, WorldMap};
+use crate::battery::Battery;
+use crate::behaviour::{self, Command, Strategies, WorldView};
+use crate::behaviour_tree::{BehaviourTree, NodePath};
+use crate::communication;
+use crate::config::{ConfigError, EndConditions, SimulationConfig};
+use crate::flow_field::FlowField;
+use crate::hierarchy::{self, Hierarchies};
+use crate::planner::Planners;
+use crate::script::RobotScript;
+use crate::sensor::{self, Sensor};
+use crate::wasm_brain::WasmBrain;
+use noise::{NoiseFn, Perlin};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashSet, VecDeque};
+use std::str::FromStr;
+
+// Affiche un message de la simulation, sauf si elle tourne en silence (batch)
+macro_rules! log {
+    ($simulation:expr, $($arg:tt)*) => {
+        if $simulation.verbose {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Cell {
+    Empty,
+    Obstacle,
+    Energy,
+    Crystal,
+    Base,
+    ReservedEnergy,
+    ReservedCrystal,
+}
+
+// Sol d'une case, indépendant de son contenu : fixe le coût de traversée pour le pathfinding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Terrain {
+    Road,
+    #[default]
+    Plain,
+    Sand,
+    Rock,
+    Mud,
+}
+
+impl Terrain {
+    pub fn cost(self) -> usize {
+        match self {
+            Terrain::Road => 1,
+            Terrain::Plain => 2,
+            Terrain::Sand => 3,
+            Terrain::Rock => 4,
+            Terrain::Mud => 6,
+        }
+    }
+
+    // Le bruit du terrain est découpé en bandes : les routes suivent les lignes où il s'annule
+    fn from_noise(value: f64) -> Terrain {
+        if value.abs() < 0.02 {
+            Terrain::Road
+        } else if value < -0.3 {
+            Terrain::Mud
+        } else if value > 0.4 {
+            Terrain::Rock
+        } else if value > 0.2 {
+            Terrain::Sand
+        } else {
+            Terrain::Plain
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Robot {
+    pub x: usize,
+    pub y: usize,
+    pub role: Role,
+    pub resource_coords: Option<(usize, usize)>,
+    pub carrying: Option<Cell>,
+    pub speed: usize,
+    pub base_speed: usize,
+    pub move_counter: usize,
+    // Cases restantes du chemin planifié, jusqu'à la cible comprise
+    #[serde(default)]
+    pub path: VecDeque<(usize, usize)>,
+    // Frontière du brouillard visée par un explorateur
+    #[serde(default)]
+    pub frontier: Option<(usize, usize)>,
+    // Nom de la stratégie qui décide de ses actions
+    #[serde(default = "Robot::default_strategy")]
+    pub strategy: String,
+    // Nom de son type de capteur (voir Simulation::sensor)
+    #[serde(default = "Robot::default_sensor")]
+    pub sensor: String,
+    // Chemin de son arbre de comportement jusqu'à l'action en cours, pour l'affichage de debug
+    #[serde(default, deserialize_with = "deserialize_active_node")]
+    pub active_node: Option<NodePath>,
+    // Dernière erreur de son script ou de son module WASM, affichée dans la fenêtre
+    #[serde(default)]
+    pub script_error: Option<String>,
+    // Cases que le robot a vues ou reçues par radio. Vide sans portée radio :
+    // il connaît alors la carte de la base
+    #[serde(default)]
+    pub knowledge: Vec<Vec<bool>>,
+    // Charge de la batterie, None sans modèle de batterie. À zéro, le robot est une épave
+    #[serde(default)]
+    pub charge: Option<u32>,
+    // Épave que la base lui a confiée, et s'il l'a accrochée
+    #[serde(default)]
+    pub rescuing: Option<usize>,
+    #[serde(default)]
+    pub towing: bool,
+}
+
+impl Robot {
+    // Robot neuf à `position`, avec la stratégie et le capteur par défaut
+    pub fn new(position: (usize, usize), role: Role, speed: usize) -> Robot {
+        Robot {
+            x: position.0,
+            y: position.1,
+            role,
+            resource_coords: None,
+            carrying: None,
+            speed,
+            base_speed: speed,
+            move_counter: 0,
+            path: VecDeque::new(),
+            frontier: None,
+            strategy: Robot::default_strategy(),
+            sensor: Robot::default_sensor(),
+            active_node: None,
+            script_error: None,
+            knowledge: Vec::new(),
+            charge: None,
+            rescuing: None,
+            towing: false,
+        }
+    }
+
+    pub fn default_speed() -> usize {
+        1
+    }
+
+    pub fn increased_speed() -> usize {
+        4
+    }
+
+    pub fn default_strategy() -> String {
+        behaviour::DEFAULT_STRATEGY.to_string()
+    }
+
+    pub fn default_sensor() -> String {
+        sensor::DEFAULT_SENSOR.to_string()
+    }
+
+    pub fn is_wreck(&self) -> bool {
+        self.charge == Some(0)
+    }
+
+    // Une ressource ou une épave fait dépenser plus de batterie
+    pub fn loaded(&self) -> bool {
+        self.carrying.is_some() || self.towing
+    }
+
+    // Parti chercher une ressource ou une épave, il rentrera chargé
+    fn returns_loaded(&self) -> bool {
+        self.loaded()
+            || self.rescuing.is_some()
+            || (self.role == Role::Extractor && self.resource_coords.is_some())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Explorer,
+    Extractor,
+}
+
+// Forme de la carte : bords infranchissables, ou tore où sortir par un bord fait entrer par l'autre
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    Bounded,
+    #[default]
+    Torus,
+}
+
+impl Topology {
+    // Case voisine dans la direction (dx, dy), None si elle sort d'une carte bornée
+    pub fn neighbour(
+        self,
+        (x, y): (usize, usize),
+        (dx, dy): (isize, isize),
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize)> {
+        let (new_x, new_y) = (x as isize + dx, y as isize + dy);
+        match self {
+            Topology::Bounded => ((0..width as isize).contains(&new_x)
+                && (0..height as isize).contains(&new_y))
+            .then_some((new_x as usize, new_y as usize)),
+            Topology::Torus => Some((
+                new_x.rem_euclid(width as isize) as usize,
+                new_y.rem_euclid(height as isize) as usize,
+            )),
+        }
+    }
+
+    // Écarts en colonnes et en lignes, en passant par les bords sur un tore
+    pub fn offsets(
+        self,
+        a: (usize, usize),
+        b: (usize, usize),
+        width: usize,
+        height: usize,
+    ) -> (usize, usize) {
+        let dx = a.0.abs_diff(b.0);
+        let dy = a.1.abs_diff(b.1);
+        match self {
+            Topology::Bounded => (dx, dy),
+            Topology::Torus => (dx.min(width - dx), dy.min(height - dy)),
+        }
+    }
+}
+
+// Coûts d'un pas droit et d'un pas en diagonale (10 * racine de 2, arrondi)
+pub const STRAIGHT_STEP_COST: usize = 10;
+pub const DIAGONAL_STEP_COST: usize = 14;
+
+// Un robot qui scanne dévoile plus loin que les cases voisines
+pub const SCAN_RADIUS: usize = 2;
+
+// Déplacements autorisés aux robots
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Movement {
+    // Haut, bas, gauche, droite
+    #[default]
+    Four,
+    // Avec les diagonales, sans couper le coin d'un obstacle
+    Eight,
+}
+
+impl Movement {
+    pub fn directions(self) -> &'static [(isize, isize)] {
+        match self {
+            Movement::Four => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+            Movement::Eight => &[
+                (-1, 0),
+                (1, 0),
+                (0, -1),
+                (0, 1),
+                (-1, -1),
+                (1, -1),
+                (-1, 1),
+                (1, 1),
+            ],
+        }
+    }
+
+    pub fn step_cost((dx, dy): (isize, isize)) -> usize {
+        if dx != 0 && dy != 0 {
+            DIAGONAL_STEP_COST
+        } else {
+            STRAIGHT_STEP_COST
+        }
+    }
+
+    // Distance de Manhattan en 4 directions, octile en 8
+    pub fn distance(self, (dx, dy): (usize, usize)) -> usize {
+        match self {
+            Movement::Four => STRAIGHT_STEP_COST * (dx + dy),
+            Movement::Eight => {
+                STRAIGHT_STEP_COST * dx.max(dy)
+                    + (DIAGONAL_STEP_COST - STRAIGHT_STEP_COST) * dx.min(dy)
+            }
+        }
+    }
+}
+
+impl FromStr for Movement {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Movement, String> {
+        match value {
+            "four" | "4" => Ok(Movement::Four),
+            "eight" | "8" => Ok(Movement::Eight),
+            _ => Err(format!("déplacement '{}' inconnu (four ou eight)", value)),
+        }
+    }
+}
+
+// Façon dont les explorateurs sans ressource en vue parcourent la carte
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exploration {
+    // Un pas au hasard par tick
+    Random,
+    // Vers la frontière du brouillard la plus proche qu'aucun autre explorateur ne vise
+    #[default]
+    Frontier,
+}
+
+impl FromStr for Exploration {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Exploration, String> {
+        match value {
+            "random" => Ok(Exploration::Random),
+            "frontier" => Ok(Exploration::Frontier),
+            _ => Err(format!(
+                "exploration '{}' inconnue (random ou frontier)",
+                value
+            )),
+        }
+    }
+}
+
+// Un pas en diagonale ne peut pas passer entre deux cases dont l'une est un obstacle
+pub(crate) fn cuts_corner(
+    map: &[Vec<Cell>],
+    topology: Topology,
+    position: (usize, usize),
+    (dx, dy): (isize, isize),
+) -> bool {
+    if dx == 0 || dy == 0 {
+        return false;
+    }
+    [(dx, 0), (0, dy)].into_iter().any(|delta| {
+        topology
+            .neighbour(position, delta, map[0].len(), map.len())
+            .is_none_or(|(x, y)| map[y][x] == Cell::Obstacle)
+    })
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Topology, String> {
+        match value {
+            "bounded" => Ok(Topology::Bounded),
+            "torus" => Ok(Topology::Torus),
+            _ => Err(format!("topologie '{}' inconnue (bounded ou torus)", value)),
+        }
+    }
+}
+
+// Ce qu'a fait un robot pendant un tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RobotAction {
+    // Attend son prochain déplacement (vitesse)
+    Wait,
+    // Pouvait agir mais est resté sur place
+    Stay,
+    // Déplacement d'une case, le passage d'un bord à l'autre compte comme un seul pas
+    Move(isize, isize),
+}
+
+#[derive(Eq, PartialEq)]
+struct Node {
+    cost: usize,
+    position: (usize, usize),
+    priority: usize,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EndReason {
+    AllResourcesCollected,
+    TargetsReached,
+    // Plus aucun robot ne peut repartir de la base ni être remorqué, faute d'énergie
+    OutOfEnergy,
+    MaxTicks,
+}
+
+// Compteurs cumulés sur l'ensemble des robots
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    // Nombre de cases parcourues
+    pub distance_travelled: u64,
+    // Ticks où un robot pouvait agir mais est resté sur place
+    pub idle_ticks: u64,
+}
+
+// Etat de la simulation, indépendant de ggez : peut être créé et avancé sans fenêtre
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Simulation {
+    pub map: Vec<Vec<Cell>>,
+    pub terrain: Vec<Vec<Terrain>>,
+    pub map_width: usize,
+    pub map_height: usize,
+    // Absente des snapshots antérieurs, qui se comportaient comme un tore
+    #[serde(default)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub movement: Movement,
+    // Les snapshots antérieurs ne connaissaient que la marche au hasard
+    #[serde(default = "random_exploration")]
+    pub exploration: Exploration,
+    // Portée radio en cases. Sans portée, tous les robots partagent la carte de la base
+    #[serde(default)]
+    pub communication_range: Option<usize>,
+    // Sans modèle de batterie, les robots se déplacent sans limite
+    #[serde(default)]
+    pub battery: Option<Battery>,
+    pub base_position: (usize, usize),
+    pub robots: Vec<Robot>,
+    // Arbres de comportement du scénario, par nom de stratégie
+    #[serde(default)]
+    pub behaviour_trees: BTreeMap<String, BehaviourTree>,
+    // Scripts Rhai du scénario, par nom de stratégie
+    #[serde(default)]
+    pub scripts: BTreeMap<String, RobotScript>,
+    // Modules WASM du scénario, par nom de stratégie
+    #[serde(default)]
+    pub brains: BTreeMap<String, WasmBrain>,
+    // Capteurs du scénario, par nom de type de robot
+    #[serde(default)]
+    pub sensors: BTreeMap<String, Sensor>,
+    // Stratégies intégrées et enregistrées par le code qui utilise la crate
+    #[serde(skip)]
+    pub strategies: Strategies,
+    pub crystal_score: u32,
+    pub energy_score: u32,
+    pub game_over: bool,
+    // Carte de la base : les cases que les robots lui ont rapportées
+    pub discovered: Vec<Vec<bool>>,
+    // Cases dans le champ d'un explorateur à ce tick. Les autres cases découvertes sont
+    // des souvenirs, que l'affichage assombrit
+    #[serde(default)]
+    pub visible: Vec<Vec<bool>>,
+    pub tick: u64,
+    pub end_reason: Option<EndReason>,
+    pub stats: RunStats,
+    #[serde(skip, default = "verbose_by_default")]
+    pub verbose: bool,
+    pub end: EndConditions,
+    // Toutes les décisions aléatoires passent par ce générateur pour qu'une seed reproduise une partie.
+    // ChaCha12 est l'algorithme de StdRng, mais son état peut être sauvegardé dans un snapshot.
+    // Il n'est absent que pendant la décision d'un robot, qui le reçoit (voir act)
+    #[serde(deserialize_with = "deserialize_rng")]
+    rng: Option<ChaCha12Rng>,
+    #[serde(skip)]
+    hierarchies: Hierarchies,
+    #[serde(skip)]
+    planners: Planners,
+    #[serde(skip)]
+    flow_field: FlowField,
+}
+
+impl Simulation {
+    pub fn new(config: &SimulationConfig) -> Result<Simulation, ConfigError> {
+        config.validate()?;
+
+        let mut rng = ChaCha12Rng::seed_from_u64(config.seed);
+        let WorldMap {
+            map,
+            terrain,
+            base_position,
+        } = match &config.map_layout {
+            Some(layout) => ascii_map::parse(layout)?,
+            None => generate_map(config, &mut rng)?,
+        };
+        let map_width = map[0].len();
+        let map_height = map.len();
+        let discovered = vec![vec![false; map_width]; map_height];
+        let knowledge = match config.communication_range {
+            Some(_) => discovered.clone(),
+            None => Vec::new(),
+        };
+
+        // Initialisation des robots
+        let robots = config
+            .robots
+            .iter()
+            .map(|robot| Robot {
+                strategy: robot.strategy.clone(),
+                sensor: robot.sensor.clone(),
+                knowledge: knowledge.clone(),
+                charge: config.battery.map(|battery| battery.capacity),
+                ..Robot::new(base_position, robot.role.clone(), robot.speed)
+            })
+            .collect();
+
+        let mut simulation = Simulation {
+            map,
+            terrain,
+            map_width,
+            map_height,
+            topology: config.topology,
+            movement: config.movement,
+            exploration: config.exploration,
+            communication_range: config.communication_range,
+            battery: config.battery,
+            base_position,
+            robots,
+            behaviour_trees: config.behaviour_trees.clone(),
+            scripts: config.scripts.clone(),
+            sensors: config.sensors.clone(),
+            brains: config.brains.clone(),
+            strategies: config.strategies.clone(),
+            crystal_score: 0,
+            energy_score: 0,
+            game_over: false,
+            visible: vec![vec![false; map_width]; map_height],
+            discovered,
+            tick: 0,
+            end_reason: None,
+            stats: RunStats::default(),
+            verbose: true,
+            end: config.end.clone(),
+            rng: Some(rng),
+            hierarchies: Hierarchies::default(),
+            planners: Planners::default(),
+            flow_field: FlowField::default(),
+        };
+        simulation.flow_field = FlowField::new(&simulation);
+        Ok(simulation)
+    }
+
+    // Champ de distances jusqu'à la base sur les cases connues, pour l'affichage de debug
+    pub fn flow_field(&self) -> Option<&FlowField> {
+        Some(&self.flow_field).filter(|field| !field.is_empty())
+    }
+
+    // Oublie les chemins gardés par les robots et les recherches de leurs planificateurs : les
+    // prochains chemins sont cherchés de zéro. Sert à mesurer ce que rapportent ces caches
+    // (voir benches/pathfinding.rs)
+    pub fn forget_paths(&mut self) {
+        for robot in &mut self.robots {
+            robot.path.clear();
+        }
+        self.planners.forget();
+    }
+
+    // Avance la simulation d'un tick et renvoie l'action de chaque robot
+    pub fn step(&mut self) -> Vec<RobotAction> {
+        if self.game_over {
+            return Vec::new();
+        }
+        if self.flow_field.is_empty() {
+            self.flow_field = FlowField::new(self);
+        }
+
+        // Récuperer les mouvements séparément
+        let mut updated_robots = self.robots.clone();
+        let mut actions = Vec::with_capacity(updated_robots.len());
+
+        for index in 0..updated_robots.len() {
+            // Frontières visées par les autres explorateurs à portée radio, déjà mis à jour ou non
+            let position = (updated_robots[index].x, updated_robots[index].y);
+            let claimed_frontiers: Vec<(usize, usize)> = updated_robots[..index]
+                .iter()
+                .chain(&self.robots[index + 1..])
+                .filter(|other| communication::in_range(self, position, (other.x, other.y)))
+                .filter_map(|robot| robot.frontier)
+                .collect();
+            let robot = &mut updated_robots[index];
+            let previous_position = (robot.x, robot.y);
+            let can_act = robot.move_counter >= robot.speed;
+            self.update_robot(index, robot, &claimed_frontiers);
+            let action = if (robot.x, robot.y) != previous_position {
+                self.stats.distance_travelled += 1;
+                self.drain(robot);
+                RobotAction::Move(
+                    wrapped_delta(previous_position.0, robot.x, self.map_width),
+                    wrapped_delta(previous_position.1, robot.y, self.map_height),
+                )
+            } else if can_act {
+                self.stats.idle_ticks += 1;
+                RobotAction::Stay
+            } else {
+                RobotAction::Wait
+            };
+            actions.push(action);
+        }
+
+        self.robots = updated_robots;
+        if self.battery.is_some() {
+            self.tow_wrecks();
+            self.recharge();
+            self.dispatch_rescues();
+        }
+        self.communicate();
+        self.update_visible();
+        self.tick += 1;
+
+        // Check si le jeu est finis
+        self.end_reason = if self.check_game_over() {
+            Some(EndReason::AllResourcesCollected)
+        } else if self.score_targets_reached() {
+            Some(EndReason::TargetsReached)
+        } else if self.robots_stranded() {
+            Some(EndReason::OutOfEnergy)
+        } else if self
+            .end
+            .max_ticks
+            .is_some_and(|max_ticks| self.tick >= max_ticks)
+        {
+            Some(EndReason::MaxTicks)
+        } else {
+            None
+        };
+        self.game_over = self.end_reason.is_some();
+
+        match self.end_reason {
+            Some(EndReason::AllResourcesCollected) => log!(
+                self,
+                "Fin du jeu! Score final - Cristaux: {}, Energies: {}",
+                self.crystal_score,
+                self.energy_score
+            ),
+            Some(EndReason::TargetsReached) => log!(
+                self,
+                "Objectifs atteints - Cristaux: {}, Energies: {}",
+                self.crystal_score,
+                self.energy_score
+            ),
+            Some(EndReason::OutOfEnergy) => log!(
+                self,
+                "Plus d'énergie pour recharger les robots - Cristaux: {}, Energies: {}",
+                self.crystal_score,
+                self.energy_score
+            ),
+            Some(EndReason::MaxTicks) => log!(
+                self,
+                "Limite de {} ticks atteinte - Cristaux: {}, Energies: {}",
+                self.tick,
+                self.crystal_score,
+                self.energy_score
+            ),
+            None => {}
+        }
+
+        actions
+    }
+
+    // Empreinte stable de tout l'état (FNV-1a sur la sérialisation), pour comparer deux parties
+    pub fn state_hash(&self) -> u64 {
+        let bytes = serde_json::to_vec(self).expect("simulation toujours sérialisable");
+        bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    // Les snapshots antérieurs n'ont pas de champ de vision jusqu'au tick suivant
+    pub fn is_visible(&self, (x, y): (usize, usize)) -> bool {
+        self.visible
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    // Les explorateurs qui attendent leur tour voient toujours autour d'eux
+    fn update_visible(&mut self) {
+        let mut visible = vec![vec![false; self.map_width]; self.map_height];
+        let sensing = self
+            .robots
+            .iter()
+            .filter(|robot| robot.role == Role::Explorer && robot.resource_coords.is_none());
+        for robot in sensing {
+            for (x, y) in self.sensor(robot).field(self, (robot.x, robot.y)) {
+                visible[y][x] = true;
+            }
+        }
+        self.visible = visible;
+    }
+
+    // Capteur d'un robot. Un scénario peut redéfinir celui par défaut
+    pub fn sensor(&self, robot: &Robot) -> Sensor {
+        self.sensors.get(&robot.sensor).copied().unwrap_or_default()
+    }
+
+    // Part des cases de la carte connues de la base
+    pub fn coverage(&self) -> f64 {
+        let discovered = self.discovered.iter().flatten().filter(|&&d| d).count();
+        discovered as f64 / (self.map_width * self.map_height) as f64
+    }
+
+    // Ce que croit savoir un robot : sa propre carte, ou celle de la base sans portée radio
+    pub fn knowledge(&self, robot: usize) -> &[Vec<bool>] {
+        self.known_map(&self.robots[robot].knowledge)
+    }
+
+    // Carte d'un robot à partir de son champ `knowledge`, vide sans portée radio
+    fn known_map<'a>(&'a self, knowledge: &'a [Vec<bool>]) -> &'a [Vec<bool>] {
+        if knowledge.is_empty() {
+            &self.discovered
+        } else {
+            knowledge
+        }
+    }
+
+    // Les groupes de robots reliés par radio mettent leurs cartes en commun, et celui qui
+    // touche la base lui transmet ce qu'il a découvert
+    fn communicate(&mut self) {
+        if self.communication_range.is_none() {
+            return;
+        }
+        for group in communication::groups(self) {
+            if group.len() < 2 {
+                continue;
+            }
+            let mut merged = vec![vec![false; self.map_width]; self.map_height];
+            for &member in &group {
+                let known = match member {
+                    Some(index) => &self.robots[index].knowledge,
+                    None => &self.discovered,
+                };
+                for (merged_row, row) in merged.iter_mut().zip(known) {
+                    for (merged_cell, &cell) in merged_row.iter_mut().zip(row) {
+                        *merged_cell |= cell;
+                    }
+                }
+            }
+            for member in group {
+                match member {
+                    Some(index) => {
+                        for (y, row) in merged.iter().enumerate() {
+                            for (x, &known) in row.iter().enumerate() {
+                                if known && !self.robots[index].knowledge[y][x] {
+                                    self.robot_learned(index, (x, y));
+                                }
+                            }
+                        }
+                        self.robots[index].knowledge.clone_from(&merged);
+                    }
+                    None => {
+                        for (y, row) in merged.iter().enumerate() {
+                            for (x, &known) in row.iter().enumerate() {
+                                if known && !self.discovered[y][x] {
+                                    self.discovered[y][x] = true;
+                                    self.base_learned((x, y));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn score_targets_reached(&self) -> bool {
+        if self.end.crystal_score.is_none() && self.end.energy_score.is_none() {
+            return false;
+        }
+        self.end
+            .crystal_score
+            .is_none_or(|target| self.crystal_score >= target)
+            && self
+                .end
+                .energy_score
+                .is_none_or(|target| self.energy_score >= target)
+    }
+
+    fn neighbour(&self, position: (usize, usize), delta: (isize, isize)) -> Option<(usize, usize)> {
+        self.topology
+            .neighbour(position, delta, self.map_width, self.map_height)
+    }
+
+    // A* du chemin le moins coûteux, départ et arrivée inclus ; avec une carte `known`, le
+    // chemin ne passe que par les cases qu'elle connaît
+    pub(crate) fn a_star_pathfinding(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        known: Option<&[Vec<bool>]>,
+    ) -> Option<Vec<(usize, usize)>> {
+        let mut open_set = BinaryHeap::new();
+        let mut came_from = std::collections::HashMap::new();
+        let mut g_score = std::collections::HashMap::new();
+        let mut f_score = std::collections::HashMap::new();
+        let mut closed_set = HashSet::new();
+
+        open_set.push(Node {
+            cost: 0,
+            position: start,
+            priority: self.heuristic(start, goal),
+        });
+
+        g_score.insert(start, 0);
+        f_score.insert(start, self.heuristic(start, goal));
+
+        while let Some(Node { position, .. }) = open_set.pop() {
+            // Déjà atteinte par un chemin moins coûteux
+            if closed_set.contains(&position) {
+                continue;
+            }
+            if position == goal {
+                let mut path = vec![position];
+                let mut current = position;
+
+                while let Some(&prev) = came_from.get(&current) {
+                    current = prev;
+                    path.push(current);
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            closed_set.insert(position);
+
+            for &delta in self.movement.directions() {
+                let Some(wrapped_pos) = self.neighbour(position, delta) else {
+                    continue;
+                };
+
+                if closed_set.contains(&wrapped_pos)
+                    || matches!(self.map[wrapped_pos.1][wrapped_pos.0], Cell::Obstacle)
+                    || cuts_corner(&self.map, self.topology, position, delta)
+                    || known.is_some_and(|known| !known[wrapped_pos.1][wrapped_pos.0])
+                {
+                    continue;
+                }
+
+                let tentative_g_score = g_score.get(&position).unwrap_or(&usize::MAX)
+                    + self.terrain[wrapped_pos.1][wrapped_pos.0].cost()
+                        * Movement::step_cost(delta);
+
+                if tentative_g_score < *g_score.get(&wrapped_pos).unwrap_or(&usize::MAX) {
+                    came_from.insert(wrapped_pos, position);
+                    g_score.insert(wrapped_pos, tentative_g_score);
+                    f_score.insert(
+                        wrapped_pos,
+                        tentative_g_score + self.heuristic(wrapped_pos, goal),
+                    );
+
+                    open_set.push(Node {
+                        cost: tentative_g_score,
+                        position: wrapped_pos,
+                        priority: tentative_g_score + self.heuristic(wrapped_pos, goal),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    // Chaque pas coûte au moins le prix d'une route, l'heuristique reste admissible
+    pub(crate) fn heuristic(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        let offsets = self.topology.offsets(a, b, self.map_width, self.map_height);
+        self.movement.distance(offsets) * Terrain::Road.cost()
+    }
+
+    // Suit le chemin gardé par le robot et ne le replanifie que s'il ne mène plus à la cible
+    // ou traverse une case devenue infranchissable. Vers la même cible, le planificateur du
+    // robot reprend sa recherche précédente (voir planner)
+    fn move_robot_towards_target(
+        &mut self,
+        index: usize,
+        robot: &mut Robot,
+        target: (usize, usize),
+        avoid_fog: bool,
+    ) {
+        let position = (robot.x, robot.y);
+        let known = avoid_fog.then(|| self.known_map(&robot.knowledge));
+        if !self.path_is_valid(position, &robot.path, target, known) {
+            robot.path = self
+                .find_path(index, position, target, avoid_fog, &robot.knowledge)
+                .map(|path| path.into_iter().skip(1).collect())
+                .unwrap_or_default();
+        }
+        if let Some((new_x, new_y)) = robot.path.pop_front() {
+            robot.x = new_x;
+            robot.y = new_y;
+        }
+    }
+
+    // D* Lite sur toute la carte, ou pathfinding hiérarchique sur les grandes cartes. Avec
+    // `avoid_fog`, le chemin reste dans la carte du robot d'indice `index` (voir known_map)
+    fn find_path(
+        &mut self,
+        index: usize,
+        start: (usize, usize),
+        goal: (usize, usize),
+        avoid_fog: bool,
+        knowledge: &[Vec<bool>],
+    ) -> Option<Vec<(usize, usize)>> {
+        if self.map_width * self.map_height < hierarchy::MIN_CELLS {
+            let mut planners = std::mem::take(&mut self.planners);
+            let known = avoid_fog.then(|| self.known_map(knowledge));
+            let path = planners
+                .robot_mut(index)
+                .find_path(self, start, goal, known);
+            self.planners = planners;
+            return path;
+        }
+        let mut hierarchies = std::mem::take(&mut self.hierarchies);
+        let hierarchy = if !avoid_fog {
+            &mut hierarchies.open
+        } else if knowledge.is_empty() {
+            &mut hierarchies.discovered
+        } else {
+            hierarchies.robot_mut(index)
+        };
+        let known = avoid_fog.then(|| self.known_map(knowledge));
+        let path = hierarchy.find_path(self, start, goal, known);
+        self.hierarchies = hierarchies;
+        path
+    }
+
+    // Dévoile une case : les robots qui évitent le brouillard peuvent maintenant y passer.
+    // Avec une portée radio, elle n'entre que dans la carte `knowledge` du robot d'indice
+    // `index`, et la base ne l'apprendra qu'en communiquant
+    fn reveal(&mut self, index: usize, knowledge: &mut [Vec<bool>], position: (usize, usize)) {
+        if !knowledge.is_empty() {
+            if !knowledge[position.1][position.0] {
+                knowledge[position.1][position.0] = true;
+                self.robot_learned(index, position);
+            }
+            return;
+        }
+        if self.discovered[position.1][position.0] {
+            return;
+        }
+        self.discovered[position.1][position.0] = true;
+        self.base_learned(position);
+    }
+
+    // Une case rejoint la carte de la base : mise à jour des caches qui en dépendent.
+    // Les obstacles ne bougent pas pendant une partie, seule la hiérarchie limitée au
+    // brouillard change : le cluster de la case est recalculé à la prochaine recherche
+    fn base_learned(&mut self, position: (usize, usize)) {
+        let (topology, width, height) = (self.topology, self.map_width, self.map_height);
+        self.hierarchies
+            .discovered
+            .mark_changed(position, topology, width, height);
+        self.planners.reveal(position);
+        let mut flow_field = std::mem::take(&mut self.flow_field);
+        flow_field.reveal(self, position);
+        self.flow_field = flow_field;
+    }
+
+    // Une case rejoint la carte du robot d'indice `index` : le cluster de sa hiérarchie est
+    // recalculé et son planificateur reprend la case à sa prochaine recherche
+    fn robot_learned(&mut self, index: usize, position: (usize, usize)) {
+        let (topology, width, height) = (self.topology, self.map_width, self.map_height);
+        self.hierarchies
+            .mark_robot_changed(index, position, topology, width, height);
+        self.planners.reveal_to_robot(index, position);
+    }
+
+    // Retour à la base en descendant le champ de distances, ou par le pathfinding
+    // si le robot n'est pas sur une case reliée à la base
+    fn move_robot_to_base(&mut self, index: usize, robot: &mut Robot, avoid_fog: bool) {
+        // Le champ couvre la carte de la base, qu'un robot avec sa propre carte ne connaît pas
+        let next_step = if robot.knowledge.is_empty() {
+            self.flow_field.next_step(self, (robot.x, robot.y))
+        } else {
+            None
+        };
+        match next_step {
+            Some((new_x, new_y)) => {
+                robot.path.clear();
+                robot.x = new_x;
+                robot.y = new_y;
+            }
+            None => self.move_robot_towards_target(index, robot, self.base_position, avoid_fog),
+        }
+    }
+
+    fn path_is_valid(
+        &self,
+        position: (usize, usize),
+        path: &VecDeque<(usize, usize)>,
+        target: (usize, usize),
+        known: Option<&[Vec<bool>]>,
+    ) -> bool {
+        if path.back() != Some(&target) {
+            return false;
+        }
+        let mut previous = position;
+        path.iter().all(|&next| {
+            let step = self
+                .movement
+                .directions()
+                .iter()
+                .find(|&&delta| self.neighbour(previous, delta) == Some(next));
+            let valid = step
+                .is_some_and(|&delta| !cuts_corner(&self.map, self.topology, previous, delta))
+                && self.map[next.1][next.0] != Cell::Obstacle
+                && known.is_none_or(|known| known[next.1][next.0]);
+            previous = next;
+            valid
+        })
+    }
+
+    // Un pas dans une direction tirée au hasard, sur place si elle est bloquée
+    pub(crate) fn move_robot_randomly(
+        x: usize,
+        y: usize,
+        directions: &[(isize, isize)],
+        rng: &mut impl Rng,
+        map: &[Vec<Cell>],
+        topology: Topology,
+    ) -> (usize, usize) {
+        if let Some((new_x, new_y)) = directions
+            .choose(rng)
+            .filter(|&&delta| !cuts_corner(map, topology, (x, y), delta))
+            .and_then(|&delta| topology.neighbour((x, y), delta, map[0].len(), map.len()))
+        {
+            if matches!(
+                map[new_y][new_x],
+                Cell::Empty | Cell::Energy | Cell::Crystal | Cell::Base
+            ) {
+                return (new_x, new_y);
+            }
+        }
+        (x, y)
+    }
+
+    // Exécute la commande choisie par la stratégie du robot
+    fn execute(&mut self, index: usize, robot: &mut Robot, command: Command) {
+        robot.frontier = None;
+        match command {
+            Command::Stay => {}
+            Command::Scan => {
+                let scan = Sensor {
+                    radius: SCAN_RADIUS,
+                    ..Sensor::default()
+                };
+                for position in scan.field(self, (robot.x, robot.y)) {
+                    self.reveal(index, &mut robot.knowledge, position);
+                }
+            }
+            Command::Step(next) => {
+                let position = (robot.x, robot.y);
+                let reachable = self.movement.directions().iter().any(|&delta| {
+                    self.neighbour(position, delta) == Some(next)
+                        && !cuts_corner(&self.map, self.topology, position, delta)
+                });
+                if reachable && self.map[next.1][next.0] != Cell::Obstacle {
+                    robot.x = next.0;
+                    robot.y = next.1;
+                }
+            }
+            Command::GoTo { target, avoid_fog } => {
+                self.move_robot_towards_target(index, robot, target, avoid_fog)
+            }
+            Command::GoToFrontier(frontier) => {
+                let position = (robot.x, robot.y);
+                self.move_robot_towards_target(index, robot, frontier, true);
+                // Une frontière qu'on n'arrive pas à approcher est laissée aux autres
+                if (robot.x, robot.y) != position {
+                    robot.frontier = Some(frontier);
+                }
+            }
+            Command::ReturnToBase { avoid_fog } => self.move_robot_to_base(index, robot, avoid_fog),
+        }
+    }
+
+    // `index` est la place du robot dans `robots`, dont il est une copie en cours de mise à jour
+    fn update_robot(
+        &mut self,
+        index: usize,
+        robot: &mut Robot,
+        claimed_frontiers: &[(usize, usize)],
+    ) {
+        if robot.is_wreck() {
+            return;
+        }
+        if robot.move_counter < robot.speed {
+            robot.move_counter += 1;
+            return;
+        }
+        robot.move_counter = 0;
+        self.act(index, robot, claimed_frontiers);
+    }
+
+    fn act(&mut self, index: usize, robot: &mut Robot, claimed_frontiers: &[(usize, usize)]) {
+        // Le générateur est sorti le temps que la stratégie lise la simulation
+        let mut rng = self
+            .rng
+            .take()
+            .expect("générateur rendu après chaque décision");
+        let world = WorldView {
+            simulation: self,
+            known: self.known_map(&robot.knowledge),
+            claimed_frontiers,
+        };
+        let previous_error = robot.script_error.take();
+        let command = if let Some(command) = self.battery_duty(robot) {
+            command
+        } else if let Some(tree) = self.behaviour_trees.get(&robot.strategy) {
+            let (command, action) = tree.tick(robot, &world, &mut rng);
+            robot.active_node = action;
+            command
+        } else if let Some(decision) = self
+            .scripts
+            .get(&robot.strategy)
+            .map(|script| script.decide(robot, &world, &mut rng))
+            .or_else(|| {
+                self.brains
+                    .get(&robot.strategy)
+                    .map(|brain| brain.decide(robot, &world, &mut rng))
+            })
+        {
+            // Un script ou un module en erreur laisse le robot sur place sans arrêter la partie
+            decision.unwrap_or_else(|err| {
+                robot.script_error = Some(err);
+                Command::Stay
+            })
+        } else {
+            self.strategies
+                .get(&robot.strategy)
+                .expect("stratégie vérifiée à la création")
+                .decide(robot, &world, &mut rng)
+        };
+        self.rng = Some(rng);
+        if let Some(err) = &robot.script_error {
+            if previous_error.as_ref() != Some(err) {
+                log!(self, "Erreur de la stratégie '{}': {}", robot.strategy, err);
+            }
+        }
+        self.execute(index, robot, command);
+        self.apply_rules(index, robot);
+
+        // Arrivé sur l'épave confiée par la base, le robot l'accroche
+        let hooked = robot.rescuing.is_some_and(|wreck| {
+            let wreck = &self.robots[wreck];
+            !robot.towing && (wreck.x, wreck.y) == (robot.x, robot.y)
+        });
+        if hooked {
+            robot.towing = true;
+            log!(
+                self,
+                "Épave accrochée en {:?}, retour à la base",
+                (robot.x, robot.y)
+            );
+        }
+    }
+
+    // Nombre de cases du retour comme le fera le robot : en descendant le champ de distances,
+    // ou avec sa propre carte par le chemin qu'il suit déjà vers la base, sinon par A* sur les
+    // cases qu'il connaît. À vol d'oiseau s'il n'est relié à la base par aucune case connue
+    fn steps_to_base(&self, robot: &Robot) -> usize {
+        let position = (robot.x, robot.y);
+        if !robot.knowledge.is_empty() {
+            let known = Some(&robot.knowledge[..]);
+            if self.path_is_valid(position, &robot.path, self.base_position, known) {
+                return robot.path.len();
+            }
+            if let Some(path) = self.a_star_pathfinding(position, self.base_position, known) {
+                return path.len() - 1;
+            }
+        } else if !self.flow_field.is_empty() {
+            let mut current = position;
+            let mut steps = 0;
+            while current != self.base_position && steps < self.map_width * self.map_height {
+                let Some(next) = self.flow_field.next_step(self, current) else {
+                    break;
+                };
+                current = next;
+                steps += 1;
+            }
+            if current == self.base_position {
+                return steps;
+            }
+        }
+        let (dx, dy) = self.topology.offsets(
+            position,
+            self.base_position,
+            self.map_width,
+            self.map_height,
+        );
+        match self.movement {
+            Movement::Four => dx + dy,
+            Movement::Eight => dx.max(dy),
+        }
+    }
+
+    // Ce que la batterie impose avant la stratégie : rentrer tant qu'il reste de quoi le
+    // faire, attendre d'être rechargé à la base, puis remorquer l'épave confiée par la base
+    fn battery_duty(&self, robot: &Robot) -> Option<Command> {
+        let battery = self.battery?;
+        let charge = robot.charge?;
+        let position = (robot.x, robot.y);
+        if position == self.base_position {
+            // Sans énergie en réserve, le robot repart dès qu'il a plus que sa réserve
+            if charge <= battery.reserve
+                || (self.energy_score > 0 && battery.worth_charging(charge))
+            {
+                return Some(Command::Stay);
+            }
+        } else if battery.must_return(charge, self.steps_to_base(robot), robot.returns_loaded()) {
+            // Avec sa propre carte, le robot rentre par les cases qu'il connaît, sur le trajet
+            // compté par steps_to_base
+            return Some(Command::ReturnToBase {
+                avoid_fog: !robot.knowledge.is_empty(),
+            });
+        }
+        let wreck = &self.robots[robot.rescuing?];
+        Some(if robot.towing {
+            Command::ReturnToBase { avoid_fog: false }
+        } else {
+            Command::GoTo {
+                target: (wreck.x, wreck.y),
+                avoid_fog: false,
+            }
+        })
+    }
+
+    // Sans énergie à la base, les robots qui y attendent sous leur réserve ne repartiront
+    // jamais, et les épaves ne seront plus remorquées
+    fn robots_stranded(&self) -> bool {
+        let Some(battery) = self.battery else {
+            return false;
+        };
+        self.energy_score == 0
+            && self.robots.iter().all(|robot| {
+                robot.is_wreck()
+                    || ((robot.x, robot.y) == self.base_position
+                        && robot.charge.is_some_and(|charge| charge <= battery.reserve))
+            })
+    }
+
+    // Un robot qui vient de se déplacer paie la case, et s'arrête net à court de batterie
+    fn drain(&mut self, robot: &mut Robot) {
+        let (Some(battery), Some(charge)) = (self.battery, robot.charge) else {
+            return;
+        };
+        let charge = charge.saturating_sub(battery.step_cost(robot.loaded()));
+        robot.charge = Some(charge);
+        if charge == 0 {
+            robot.rescuing = None;
+            robot.towing = false;
+            robot.path.clear();
+            robot.frontier = None;
+            log!(
+                self,
+                "Robot à court de batterie en {:?}, en attente de remorquage",
+                (robot.x, robot.y)
+            );
+        }
+    }
+
+    // Les épaves suivent leur remorqueur, qui les lâche à la base
+    fn tow_wrecks(&mut self) {
+        for index in 0..self.robots.len() {
+            let robot = &self.robots[index];
+            let Some(wreck) = robot.rescuing.filter(|_| robot.towing) else {
+                continue;
+            };
+            let position = (robot.x, robot.y);
+            self.robots[wreck].x = position.0;
+            self.robots[wreck].y = position.1;
+            if position == self.base_position {
+                self.robots[index].rescuing = None;
+                self.robots[index].towing = false;
+                log!(self, "Épave ramenée à la base");
+            }
+        }
+    }
+
+    // À la base, un robot qui a de la place dépense une énergie en réserve par tick
+    fn recharge(&mut self) {
+        let Some(battery) = self.battery else {
+            return;
+        };
+        for index in 0..self.robots.len() {
+            let robot = &self.robots[index];
+            let Some(charge) = robot.charge else {
+                continue;
+            };
+            if (robot.x, robot.y) != self.base_position
+                || !battery.worth_charging(charge)
+                || self.energy_score == 0
+            {
+                continue;
+            }
+            self.energy_score -= 1;
+            self.robots[index].charge =
+                Some((charge + battery.charge_per_energy).min(battery.capacity));
+            if charge == 0 {
+                log!(self, "Épave rechargée, le robot repart");
+            }
+        }
+    }
+
+    // La base confie chaque épave hors de la base à l'explorateur libre le plus proche
+    fn dispatch_rescues(&mut self) {
+        let wrecks: Vec<usize> = (0..self.robots.len())
+            .filter(|&index| self.robots[index].is_wreck())
+            .collect();
+        // Une épave rechargée n'a plus besoin d'aide
+        for robot in &mut self.robots {
+            if robot.rescuing.is_some_and(|wreck| !wrecks.contains(&wreck)) {
+                robot.rescuing = None;
+                robot.towing = false;
+            }
+        }
+        for wreck in wrecks {
+            let position = (self.robots[wreck].x, self.robots[wreck].y);
+            if position == self.base_position
+                || self
+                    .robots
+                    .iter()
+                    .any(|robot| robot.rescuing == Some(wreck))
+            {
+                continue;
+            }
+            let rescuer = self
+                .robots
+                .iter()
+                .enumerate()
+                .filter(|(_, robot)| {
+                    !robot.is_wreck()
+                        && robot.rescuing.is_none()
+                        && robot.role == Role::Explorer
+                        && robot.resource_coords.is_none()
+                })
+                .min_by_key(|(_, robot)| {
+                    let (dx, dy) = self.topology.offsets(
+                        position,
+                        (robot.x, robot.y),
+                        self.map_width,
+                        self.map_height,
+                    );
+                    dx + dy
+                })
+                .map(|(index, _)| index);
+            if let Some(rescuer) = rescuer {
+                self.robots[rescuer].rescuing = Some(wreck);
+                log!(
+                    self,
+                    "Un explorateur part remorquer l'épave en {:?}",
+                    position
+                );
+            }
+        }
+    }
+
+    // Règles du jeu après le déplacement : brouillard et ressources pour les explorateurs,
+    // ramassage et dépôt pour les extracteurs
+    fn apply_rules(&mut self, index: usize, robot: &mut Robot) {
+        match robot.role {
+            Role::Explorer => {
+                if let Some(resource_coords) = robot.resource_coords {
+                    if (robot.x, robot.y) == self.base_position {
+                        // Robot explorateur passe à robot extracteur et va chercher la ressource
+                        robot.role = Role::Extractor;
+                        log!(
+                            self,
+                            "Déploiement du Robot extracteur, il part chercher la ressource {:?}",
+                            resource_coords
+                        );
+                    }
+                } else {
+                    // Dévoile le champ du capteur
+                    let sensor = self.sensor(robot);
+                    let field = sensor.field(self, (robot.x, robot.y));
+                    for &position in &field {
+                        self.reveal(index, &mut robot.knowledge, position);
+                    }
+
+                    // Check si une ressource du champ est repérée
+                    if let Some((check_x, check_y)) = self.detect_resource(&sensor, &field) {
+                        let resource_type = self.map[check_y][check_x].clone();
+                        robot.resource_coords = Some((check_x, check_y));
+                        robot.frontier = None;
+                        self.map[check_y][check_x] = match resource_type {
+                            Cell::Crystal => Cell::ReservedCrystal,
+                            Cell::Energy => Cell::ReservedEnergy,
+                            _ => unreachable!(),
+                        };
+                        log!(
+                            self,
+                            "Robot explorateur à trouver une ressource {:?}, retour à la base",
+                            (check_x, check_y)
+                        );
+                    }
+                }
+            }
+            Role::Extractor => {
+                if let Some(resource_coords) = robot.resource_coords {
+                    if robot.carrying.is_none() {
+                        if (robot.x, robot.y) == resource_coords {
+                            // Collecter la ressource
+                            robot.speed = Robot::increased_speed();
+                            if matches!(
+                                self.map[robot.y][robot.x],
+                                Cell::ReservedCrystal | Cell::ReservedEnergy
+                            ) {
+                                robot.carrying = Some(match self.map[robot.y][robot.x] {
+                                    Cell::ReservedCrystal => Cell::Crystal,
+                                    Cell::ReservedEnergy => Cell::Energy,
+                                    _ => unreachable!(),
+                                });
+                                self.map[robot.y][robot.x] = Cell::Empty;
+                                log!(self, "Robot extracteur a récupéré la ressource {:?}, retour à la base", (robot.x, robot.y));
+                            }
+                        }
+                    } else if (robot.x, robot.y) == self.base_position {
+                        match robot.carrying {
+                            Some(Cell::Crystal) => {
+                                self.crystal_score += 1;
+                                log!(
+                                    self,
+                                    "Cristal déposé à la base. Score: {}",
+                                    self.crystal_score
+                                );
+                            }
+                            Some(Cell::Energy) => {
+                                self.energy_score += 1;
+                                log!(
+                                    self,
+                                    "Energie déposée à la base. Score: {}",
+                                    self.energy_score
+                                );
+                            }
+                            _ => {}
+                        }
+                        // Passe de l'extracteur à l'explorateur
+                        robot.role = Role::Explorer;
+                        robot.speed = robot.base_speed;
+                        robot.carrying = None;
+                        robot.resource_coords = None;
+                        log!(self, "Envoie du robot explorateur");
+                    }
+                }
+            }
+        }
+    }
+
+    // Première ressource du champ repérée par le capteur. Un capteur sûr ne tire rien,
+    // pour que les parties sans capteur configuré restent les mêmes
+    fn detect_resource(
+        &mut self,
+        sensor: &Sensor,
+        field: &[(usize, usize)],
+    ) -> Option<(usize, usize)> {
+        field.iter().copied().find(|&(x, y)| {
+            matches!(self.map[y][x], Cell::Crystal | Cell::Energy)
+                && (sensor.detection_probability >= 1.0
+                    || self
+                        .rng
+                        .as_mut()
+                        .expect("générateur rendu après chaque décision")
+                        .gen_bool(sensor.detection_probability))
+        })
+    }
+
+    pub fn check_game_over(&self) -> bool {
+        !self.map.iter().any(|row| {
+            row.iter().any(|cell| {
+                matches!(
+                    cell,
+                    Cell::Crystal | Cell::Energy | Cell::ReservedCrystal | Cell::ReservedEnergy
+                )
+            })
+        })
+    }
+}
+
+// Génère une carte par bruit de Perlin et renvoie la position de la base
+// Un snapshot contient toujours le générateur
+fn deserialize_rng<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<ChaCha12Rng>, D::Error> {
+    ChaCha12Rng::deserialize(deserializer).map(Some)
+}
+
+// Les snapshots antérieurs ne gardaient que le nom de l'action : le chemin manque alors
+// jusqu'à la prochaine décision du robot
+fn deserialize_active_node<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NodePath>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ActiveNode {
+        Path(NodePath),
+        Action(serde::de::IgnoredAny),
+    }
+    Ok(match Option::<ActiveNode>::deserialize(deserializer)? {
+        Some(ActiveNode::Path(path)) => Some(path),
+        Some(ActiveNode::Action(_)) | None => None,
+    })
+}
+
+fn generate_map(config: &SimulationConfig, rng: &mut ChaCha12Rng) -> Result<WorldMap, ConfigError> {
+    let noise = Perlin::new(rng.gen());
+    let map_width = config.map_width;
+    let map_height = config.map_height;
+    let noise_threshold = config.noise_threshold;
+    let mut map = vec![vec![Cell::Empty; map_width]; map_height];
+
+    // Generation d'obstacle aléatoire
+    for (y, row) in map.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let nx = x as f64 / map_width as f64;
+            let ny = y as f64 / map_height as f64;
+            let val = noise.get([
+                nx * config.noise_scale,
+                ny * config.noise_scale,
+                rng.gen::<f64>(),
+            ]);
+            if val > noise_threshold {
+                *cell = Cell::Obstacle;
+            }
+        }
+    }
+
+    // creation d'obstacles pour la bordure de map
+    map[0] = vec![Cell::Obstacle; map_width];
+    map[map_height - 1] = vec![Cell::Obstacle; map_width];
+    for row in map.iter_mut() {
+        row[0] = Cell::Obstacle;
+        row[map_width - 1] = Cell::Obstacle;
+    }
+
+    // Place la base à une position random
+    let base_position = place_randomly(&mut map, rng, Cell::Base, 1).ok_or_else(|| {
+        ConfigError::Invalid(
+            "aucune case libre pour placer la base, baissez noise_threshold".to_string(),
+        )
+    })?;
+
+    // enleve les obstacles autour de la base
+    let radius = config.base_clear_radius as isize;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let (x, y) = (
+                (base_position.0 as isize + dx).clamp(0, map_width as isize - 1) as usize,
+                (base_position.1 as isize + dy).clamp(0, map_height as isize - 1) as usize,
+            );
+            map[y][x] = Cell::Empty;
+        }
+    }
+
+    // Place aleatoirement les ressources
+    let free_cells = count_empty_cells(&map);
+    let resource_count = config.energy_count + config.crystal_count;
+    if resource_count > free_cells {
+        return Err(ConfigError::Invalid(format!(
+            "{} ressources demandées mais seulement {} cases libres sur la carte générée",
+            resource_count, free_cells
+        )));
+    }
+    place_randomly(&mut map, rng, Cell::Energy, config.energy_count);
+    place_randomly(&mut map, rng, Cell::Crystal, config.crystal_count);
+
+    // S'assurer que la base à une bonne position
+    map[base_position.1][base_position.0] = Cell::Base;
+
+    // Deuxième couche de bruit pour le terrain, tirée après les ressources pour garder leur placement
+    let terrain_noise = Perlin::new(rng.gen());
+    let terrain = (0..map_height)
+        .map(|y| {
+            (0..map_width)
+                .map(|x| {
+                    let nx = x as f64 / map_width as f64;
+                    let ny = y as f64 / map_height as f64;
+                    Terrain::from_noise(terrain_noise.get([
+                        nx * config.terrain_scale,
+                        ny * config.terrain_scale,
+                        0.5,
+                    ]))
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(WorldMap {
+        map,
+        terrain,
+        base_position,
+    })
+}
+
+fn place_randomly(
+    map: &mut [Vec<Cell>],
+    rng: &mut impl Rng,
+    cell_type: Cell,
+    quantity: usize,
+) -> Option<(usize, usize)> {
+    // Sans assez de cases libres la recherche aléatoire ne terminerait jamais
+    if count_empty_cells(map) < quantity {
+        return None;
+    }
+
+    let map_height = map.len();
+    let map_width = map[0].len();
+
+    let mut placed_positions = Vec::new();
+
+    for _ in 0..quantity {
+        let mut placed = false;
+        while !placed {
+            let x = rng.gen_range(0..map_width);
+            let y = rng.gen_range(0..map_height);
+            if matches!(map[y][x], Cell::Empty) {
+                map[y][x] = cell_type.clone();
+                placed_positions.push((x, y));
+                placed = true;
+            }
+        }
+    }
+
+    if !placed_positions.is_empty() {
+        Some(placed_positions[0])
+    } else {
+        None
+    }
+}
+
+fn wrapped_delta(from: usize, to: usize, size: usize) -> isize {
+    let delta = to as isize - from as isize;
+    if delta > 1 {
+        delta - size as isize
+    } else if delta < -1 {
+        delta + size as isize
+    } else {
+        delta
+    }
+}
+
+fn random_exploration() -> Exploration {
+    Exploration::Random
+}
+
+fn verbose_by_default() -> bool {
+    true
+}
+
+fn count_empty_cells(map: &[Vec<Cell>]) -> usize {
+    map.iter()
+        .flatten()
+        .filter(|cell| matches!(cell, Cell::Empty))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RobotConfig;
+    use rand::rngs::StdRng;
+    use rstest::rstest;
+
+    fn create_test_simulation() -> Simulation {
+        let seed = 0;
+        Simulation::new(&SimulationConfig {
+            seed,
+            ..SimulationConfig::default()
+        })
+        .unwrap()
+    }
+
+    // Carte 40x30 sans obstacles ni bordure
+    fn open_simulation(topology: Topology) -> Simulation {
+        let mut simulation = create_test_simulation();
+        simulation.topology = topology;
+        simulation.map = vec![vec![Cell::Empty; simulation.map_width]; simulation.map_height];
+        simulation.terrain =
+            vec![vec![Terrain::Plain; simulation.map_width]; simulation.map_height];
+        simulation
+    }
+
+    #[test]
+    fn test_heuristic() {
+        let simulation = create_test_simulation();
+        assert_eq!(simulation.heuristic((0, 0), (3, 4)), 70);
+        assert_eq!(simulation.heuristic((2, 3), (5, 1)), 50);
+    }
+
+    #[test]
+    fn test_octile_heuristic() {
+        let mut simulation = create_test_simulation();
+        simulation.movement = Movement::Eight;
+        assert_eq!(simulation.heuristic((0, 0), (3, 4)), 3 * 14 + 10);
+        assert_eq!(simulation.heuristic((2, 3), (5, 1)), 2 * 14 + 10);
+        assert_eq!(simulation.heuristic((0, 0), (39, 29)), 14);
+    }
+
+    #[rstest]
+    #[case(Topology::Torus, 10, 20)]
+    #[case(Topology::Bounded, 390, 290 + 390)]
+    fn test_heuristic_across_edges(
+        #[case] topology: Topology,
+        #[case] horizontal: usize,
+        #[case] diagonal: usize,
+    ) {
+        let simulation = open_simulation(topology);
+        assert_eq!(simulation.heuristic((0, 5), (39, 5)), horizontal);
+        assert_eq!(simulation.heuristic((5, 0), (5, 29)), horizontal.min(290));
+        assert_eq!(simulation.heuristic((0, 0), (39, 29)), diagonal);
+    }
+
+    #[test]
+    fn test_wrap_position() {
+        assert_eq!(
+            Topology::Torus.neighbour((39, 29), (0, 0), 40, 30),
+            Some((39, 29))
+        );
+        assert_eq!(
+            Topology::Torus.neighbour((39, 29), (1, 1), 40, 30),
+            Some((0, 0))
+        );
+        assert_eq!(
+            Topology::Torus.neighbour((0, 0), (-1, -1), 40, 30),
+            Some((39, 29))
+        );
+    }
+
+    #[rstest]
+    #[case((0, 5), (-1, 0), Some((39, 5)))]
+    #[case((39, 5), (1, 0), Some((0, 5)))]
+    #[case((5, 0), (0, -1), Some((5, 29)))]
+    #[case((5, 29), (0, 1), Some((5, 0)))]
+    #[case((5, 5), (1, 1), Some((6, 6)))]
+    fn test_neighbour_across_edges(
+        #[case] position: (usize, usize),
+        #[case] delta: (isize, isize),
+        #[case] torus: Option<(usize, usize)>,
+    ) {
+        assert_eq!(Topology::Torus.neighbour(position, delta, 40, 30), torus);
+        let inside = torus.filter(|_| position == (5, 5));
+        assert_eq!(Topology::Bounded.neighbour(position, delta, 40, 30), inside);
+    }
+
+    #[rstest]
+    #[case(Topology::Torus, 2, 2)]
+    #[case(Topology::Bounded, 40, 30)]
+    fn test_a_star_across_edges(
+        #[case] topology: Topology,
+        #[case] horizontal: usize,
+        #[case] vertical: usize,
+    ) {
+        let simulation = open_simulation(topology);
+        let path = simulation
+            .a_star_pathfinding((0, 5), (39, 5), None)
+            .unwrap();
+        assert_eq!(path.len(), horizontal);
+        let path = simulation
+            .a_star_pathfinding((5, 29), (5, 0), None)
+            .unwrap();
+        assert_eq!(path.len(), vertical);
+    }
+
+    #[rstest]
+    #[case(Topology::Torus, (39, 5), (5, 29))]
+    #[case(Topology::Bounded, (0, 5), (5, 0))]
+    fn test_move_robot_randomly_across_edges(
+        #[case] topology: Topology,
+        #[case] left: (usize, usize),
+        #[case] up: (usize, usize),
+    ) {
+        let map = vec![vec![Cell::Empty; 40]; 30];
+        let mut rng = StdRng::seed_from_u64(0);
+        let moved = Simulation::move_robot_randomly(0, 5, &[(-1, 0)], &mut rng, &map, topology);
+        assert_eq!(moved, left);
+        let moved = Simulation::move_robot_randomly(5, 0, &[(0, -1)], &mut rng, &map, topology);
+        assert_eq!(moved, up);
+    }
+
+    #[rstest]
+    #[case(Topology::Torus, 9)]
+    #[case(Topology::Bounded, 4)]
+    fn test_fog_reveal_in_corner(#[case] topology: Topology, #[case] revealed: usize) {
+        let mut simulation = open_simulation(topology);
+        // Entouré d'obstacles, le robot reste dans son coin
+        for (x, y) in [(1, 0), (0, 1), (39, 0), (0, 29)] {
+            simulation.map[y][x] = Cell::Obstacle;
+        }
+        let mut robot = Robot::new((0, 0), Role::Explorer, 0);
+        simulation.update_robot(0, &mut robot, &[]);
+        assert_eq!((robot.x, robot.y), (0, 0));
+        let count = simulation
+            .discovered
+            .iter()
+            .flatten()
+            .filter(|&&d| d)
+            .count();
+        assert_eq!(count, revealed);
+        assert_eq!(simulation.discovered[29][39], topology == Topology::Torus);
+    }
+
+    #[test]
+    fn test_move_robot_randomly() {
+        let map = vec![vec![Cell::Empty; 5]; 5];
+        let directions = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        let mut rng = StdRng::seed_from_u64(0);
+        let (x, y) =
+            Simulation::move_robot_randomly(2, 2, &directions, &mut rng, &map, Topology::Torus);
+        assert!(x < 5 && y < 5);
+    }
+
+    #[rstest]
+    #[case(Cell::Energy, true)]
+    #[case(Cell::Crystal, true)]
+    #[case(Cell::Obstacle, true)]
+    fn test_place_randomly(#[case] cell_type: Cell, #[case] expected: bool) {
+        let mut map = vec![vec![Cell::Empty; 5]; 5];
+        let mut rng = StdRng::seed_from_u64(0);
+        let result = place_randomly(&mut map, &mut rng, cell_type.clone(), 1);
+        assert_eq!(result.is_some(), expected);
+        if let Some((x, y)) = result {
+            assert_eq!(map[y][x], cell_type);
+        }
+    }
+
+    #[test]
+    fn test_a_star_pathfinding() {
+        // La carte générée est bordée d'obstacles : (0, 0) serait un mur
+        let mut simulation = open_simulation(Topology::Torus);
+        simulation.map[1][0] = Cell::Obstacle;
+        simulation.map[1][1] = Cell::Obstacle;
+        simulation.map[1][2] = Cell::Obstacle;
+        simulation.map[0][2] = Cell::Obstacle;
+
+        // Le mur coupe la ligne 1 : le chemin fait le tour par le bord opposé du tore
+        let west = simulation.map_width - 1;
+        if let Some(path) = simulation.a_star_pathfinding((0, 0), (2, 2), None) {
+            assert_eq!(
+                path,
+                vec![
+                    (0, 0),
+                    (west, 0),
+                    (west, 1),
+                    (west, 2),
+                    (0, 2),
+                    (1, 2),
+                    (2, 2)
+                ]
+            );
+        } else {
+            panic!("Pathfinding failed");
+        }
+    }
+
+    #[test]
+    fn test_a_star_prefers_roads_over_mud() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        simulation.terrain[5] = vec![Terrain::Mud; simulation.map_width];
+        for x in 0..=6 {
+            simulation.terrain[4][x] = Terrain::Road;
+        }
+
+        // 6 cases de boue tout droit coûtent 360, le détour par la route 80
+        let path = simulation.a_star_pathfinding((0, 5), (6, 5), None).unwrap();
+        assert_eq!(path.len(), 9);
+        assert!(path[1..path.len() - 1].iter().all(|&(_, y)| y == 4));
+
+        // Sans route, le chemin le plus court reste le moins coûteux sur un terrain uniforme
+        simulation.terrain[4] = vec![Terrain::Plain; simulation.map_width];
+        simulation.terrain[5] = vec![Terrain::Plain; simulation.map_width];
+        let path = simulation.a_star_pathfinding((0, 5), (6, 5), None).unwrap();
+        assert_eq!(path.len(), 7);
+    }
+
+    #[test]
+    fn test_a_star_eight_directions() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        simulation.movement = Movement::Eight;
+        let path = simulation.a_star_pathfinding((0, 0), (5, 5), None).unwrap();
+        assert_eq!(path, (0..=5).map(|i| (i, i)).collect::<Vec<_>>());
+        let path = simulation.a_star_pathfinding((0, 0), (5, 2), None).unwrap();
+        assert_eq!(path.len(), 6);
+    }
+
+    #[test]
+    fn test_a_star_does_not_cut_corners() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        simulation.movement = Movement::Eight;
+        simulation.map[5][6] = Cell::Obstacle;
+        let path = simulation.a_star_pathfinding((5, 5), (6, 6), None).unwrap();
+        assert_eq!(path, vec![(5, 5), (5, 6), (6, 6)]);
+
+        // Au bord d'une carte bornée, la case manquante compte comme un obstacle
+        assert!(cuts_corner(
+            &simulation.map,
+            Topology::Bounded,
+            (0, 3),
+            (-1, 1)
+        ));
+        assert!(!cuts_corner(
+            &simulation.map,
+            Topology::Bounded,
+            (1, 3),
+            (-1, 1)
+        ));
+    }
+
+    #[test]
+    fn test_move_robot_randomly_diagonal() {
+        let mut map = vec![vec![Cell::Empty; 5]; 5];
+        let mut rng = StdRng::seed_from_u64(0);
+        let diagonal = [(1, 1)];
+        let moved =
+            Simulation::move_robot_randomly(2, 2, &diagonal, &mut rng, &map, Topology::Torus);
+        assert_eq!(moved, (3, 3));
+        map[2][3] = Cell::Obstacle;
+        let moved =
+            Simulation::move_robot_randomly(2, 2, &diagonal, &mut rng, &map, Topology::Torus);
+        assert_eq!(moved, (2, 2));
+    }
+
+    #[test]
+    fn test_generated_terrain_has_every_type() {
+        let simulation = Simulation::new(&SimulationConfig {
+            map_width: 80,
+            map_height: 60,
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        assert_eq!(simulation.terrain.len(), simulation.map_height);
+        for terrain in [
+            Terrain::Road,
+            Terrain::Plain,
+            Terrain::Sand,
+            Terrain::Rock,
+            Terrain::Mud,
+        ] {
+            assert!(
+                simulation.terrain.iter().flatten().any(|&t| t == terrain),
+                "{:?}",
+                terrain
+            );
+        }
+    }
+
+    #[test]
+    fn test_update_robot() {
+        let mut simulation = create_test_simulation();
+        let mut robot = Robot::new(
+            simulation.base_position,
+            Role::Explorer,
+            Robot::default_speed(),
+        );
+
+        simulation.update_robot(0, &mut robot, &[]);
+        assert_eq!(robot.move_counter, 1);
+
+        simulation.update_robot(0, &mut robot, &[]);
+        assert!(robot.x != simulation.base_position.0 || robot.y != simulation.base_position.1);
+    }
+
+    fn robot_at(position: (usize, usize)) -> Robot {
+        Robot::new(position, Role::Extractor, 0)
+    }
+
+    #[test]
+    fn test_robot_follows_cached_path() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        let mut robot = robot_at((2, 5));
+        simulation.move_robot_towards_target(0, &mut robot, (10, 5), false);
+        assert_eq!((robot.x, robot.y), (3, 5));
+        assert_eq!(robot.path.len(), 7);
+
+        // Le chemin gardé est suivi tel quel, même s'il n'est pas celui qu'A* donnerait maintenant
+        robot.path = VecDeque::from(vec![
+            (3, 6),
+            (4, 6),
+            (5, 6),
+            (6, 6),
+            (7, 6),
+            (8, 6),
+            (9, 6),
+            (10, 6),
+            (10, 5),
+        ]);
+        simulation.move_robot_towards_target(0, &mut robot, (10, 5), false);
+        assert_eq!((robot.x, robot.y), (3, 6));
+        assert_eq!(robot.path.len(), 8);
+    }
+
+    #[test]
+    fn test_step_command_is_checked() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        simulation.map[5][3] = Cell::Obstacle;
+        let mut robot = robot_at((2, 5));
+        // Case lointaine puis obstacle : le robot ne bouge pas
+        simulation.execute(0, &mut robot, Command::Step((4, 5)));
+        simulation.execute(0, &mut robot, Command::Step((3, 5)));
+        assert_eq!((robot.x, robot.y), (2, 5));
+        simulation.execute(0, &mut robot, Command::Step((2, 6)));
+        assert_eq!((robot.x, robot.y), (2, 6));
+    }
+
+    #[test]
+    fn test_robot_returns_along_flow_field() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        simulation.base_position = (5, 5);
+        for x in 5..=12 {
+            simulation.discovered[5][x] = true;
+            simulation.discovered[6][x] = true;
+        }
+        simulation.flow_field = FlowField::new(&simulation);
+
+        // Sur une case connue, le robot descend le champ sans planifier de chemin
+        let mut robot = robot_at((12, 6));
+        simulation.move_robot_to_base(0, &mut robot, true);
+        assert_eq!((robot.x, robot.y), (11, 6));
+        assert!(robot.path.is_empty());
+
+        // Hors du champ, il se rabat sur le pathfinding
+        let mut robot = robot_at((12, 9));
+        simulation.move_robot_to_base(0, &mut robot, false);
+        assert_eq!(robot.path.back(), Some(&(5, 5)));
+    }
+
+    #[test]
+    fn test_robot_replans_when_path_is_blocked() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        let mut robot = robot_at((2, 5));
+        simulation.move_robot_towards_target(0, &mut robot, (10, 5), false);
+        let blocked = robot.path[2];
+        simulation.map[blocked.1][blocked.0] = Cell::Obstacle;
+
+        simulation.move_robot_towards_target(0, &mut robot, (10, 5), false);
+        assert!(!robot.path.contains(&blocked));
+        assert_eq!(robot.path.back(), Some(&(10, 5)));
+    }
+
+    #[test]
+    fn test_robot_replans_for_new_target_or_fog() {
+        let mut simulation = open_simulation(Topology::Bounded);
+        let mut robot = robot_at((2, 5));
+        simulation.move_robot_towards_target(0, &mut robot, (10, 5), false);
+        assert!(!simulation.path_is_valid((3, 5), &robot.path, (10, 6), None));
+        // Un extracteur ne passe que par des cases découvertes
+        assert!(!simulation.path_is_valid(
+            (3, 5),
+            &robot.path,
+            (10, 5),
+            Some(&simulation.discovered)
+        ));
+        for &(x, y) in &robot.path {
+            simulation.discovered[y][x] = true;
+        }
+        assert!(simulation.path_is_valid(
+            (3, 5),
+            &robot.path,
+            (10, 5),
+            Some(&simulation.discovered)
+        ));
+        // Ni un robot déplacé hors de son chemin
+        assert!(!simulation.path_is_valid((3, 8), &robot.path, (10, 5), None));
+    }
+
+    #[test]
+    fn test_check_game_over() {
+        let mut simulation = create_test_simulation();
+        assert!(!simulation.check_game_over());
+
+        simulation
+            .map
+            .iter_mut()
+            .flatten()
+            .for_each(|cell| *cell = Cell::Empty);
+        assert!(simulation.check_game_over());
+    }
+
+    #[test]
+    fn test_robot_finds_resource() {
+        let mut simulation = create_test_simulation();
+        let mut robot = Robot {
+            move_counter: Robot::default_speed(),
+            ..Robot::new(
+                simulation.base_position,
+                Role::Explorer,
+                Robot::default_speed(),
+            )
+        };
+
+        simulation.map[2][2] = Cell::Crystal;
+        robot.x = 1;
+        robot.y = 1;
+        simulation.update_robot(0, &mut robot, &[]);
+
+        assert_eq!(robot.resource_coords, Some((2, 2)));
+        assert_eq!(simulation.map[2][2], Cell::ReservedCrystal);
+    }
+
+    #[test]
+    fn test_sensor_reveals_and_detects() {
+        let mut simulation = open_simulation(Topology::Torus);
+        simulation.sensors.insert(
+            "radar".to_string(),
+            Sensor {
+                radius: 3,
+                detection_probability: 0.0,
+                ..Sensor::default()
+            },
+        );
+        let mut robot = simulation.robots[0].clone();
+        robot.sensor = "radar".to_string();
+        (robot.x, robot.y) = (10, 10);
+        simulation.map[12][12] = Cell::Crystal;
+
+        simulation.apply_rules(0, &mut robot);
+        assert!(simulation.discovered[13][13]);
+        assert!(!simulation.discovered[14][10]);
+        // Vue mais pas repérée
+        assert_eq!(robot.resource_coords, None);
+        assert_eq!(simulation.map[12][12], Cell::Crystal);
+
+        simulation
+            .sensors
+            .get_mut("radar")
+            .unwrap()
+            .detection_probability = 1.0;
+        simulation.apply_rules(0, &mut robot);
+        assert_eq!(robot.resource_coords, Some((12, 12)));
+    }
+
+    #[test]
+    fn test_visible_cells_follow_explorers() {
+        let mut simulation = create_test_simulation();
+        simulation.verbose = false;
+        for _ in 0..50 {
+            simulation.step();
+        }
+        let visible: Vec<(usize, usize)> = (0..simulation.map_height)
+            .flat_map(|y| (0..simulation.map_width).map(move |x| (x, y)))
+            .filter(|&position| simulation.is_visible(position))
+            .collect();
+        assert!(!visible.is_empty());
+        for &(x, y) in &visible {
+            assert!(simulation.discovered[y][x]);
+            let near_robot = simulation.robots.iter().any(|robot| {
+                let (dx, dy) = simulation.topology.offsets(
+                    (x, y),
+                    (robot.x, robot.y),
+                    simulation.map_width,
+                    simulation.map_height,
+                );
+                dx <= 1 && dy <= 1
+            });
+            assert!(near_robot);
+        }
+    }
+
+    fn battery_simulation() -> Simulation {
+        let mut simulation = open_simulation(Topology::Torus);
+        simulation.verbose = false;
+        simulation.battery = Some(Battery::default());
+        for robot in &mut simulation.robots {
+            robot.charge = Some(Battery::default().capacity);
+        }
+        simulation
+    }
+
+    #[test]
+    fn test_battery_drains_more_when_loaded() {
+        let mut simulation = battery_simulation();
+        let mut robot = simulation.robots[0].clone();
+        simulation.drain(&mut robot);
+        assert_eq!(robot.charge, Some(299));
+        robot.carrying = Some(Cell::Energy);
+        simulation.drain(&mut robot);
+        assert_eq!(robot.charge, Some(297));
+
+        robot.charge = Some(1);
+        simulation.drain(&mut robot);
+        assert!(robot.is_wreck());
+    }
+
+    #[test]
+    fn test_robot_returns_before_running_dry() {
+        let simulation = battery_simulation();
+        let (base_x, base_y) = simulation.base_position;
+        let mut robot = simulation.robots[0].clone();
+        (robot.x, robot.y) = ((base_x + 5) % simulation.map_width, base_y);
+        let reserve = Battery::default().reserve;
+
+        robot.charge = Some(reserve + 6);
+        assert_eq!(simulation.battery_duty(&robot), None);
+        robot.charge = Some(reserve + 5);
+        assert_eq!(
+            simulation.battery_duty(&robot),
+            Some(Command::ReturnToBase { avoid_fog: false })
+        );
+        // Un extracteur qui va chercher une ressource prévoit un retour chargé
+        robot.charge = Some(reserve + 6);
+        robot.role = Role::Extractor;
+        robot.resource_coords = Some((base_x, base_y + 8));
+        assert_eq!(
+            simulation.battery_duty(&robot),
+            Some(Command::ReturnToBase { avoid_fog: false })
+        );
+    }
+
+    #[test]
+    fn test_robot_with_own_map_counts_return_around_obstacles() {
+        let mut simulation = battery_simulation();
+        let (base_x, base_y) = simulation.base_position;
+        let (width, height) = (simulation.map_width, simulation.map_height);
+        simulation.communication_range = Some(3);
+        // Un mur entre le robot et la base : le retour doit en faire le tour
+        for dy in 0..7 {
+            simulation.map[(base_y + height - 3 + dy) % height][(base_x + 2) % width] =
+                Cell::Obstacle;
+        }
+        let mut robot = simulation.robots[0].clone();
+        (robot.x, robot.y) = ((base_x + 5) % width, base_y);
+        robot.knowledge = vec![vec![true; width]; height];
+        let detour = simulation.steps_to_base(&robot);
+        assert!(detour > 5);
+
+        // De quoi faire 5 cases à vol d'oiseau, pas le tour du mur
+        robot.charge = Some(Battery::default().reserve + 6);
+        let command = simulation.battery_duty(&robot);
+        assert_eq!(command, Some(Command::ReturnToBase { avoid_fog: true }));
+        // Le robot suit le trajet qui a été compté
+        simulation.execute(0, &mut robot, command.unwrap());
+        assert_eq!(robot.path.len(), detour - 1);
+        assert_eq!(simulation.steps_to_base(&robot), detour - 1);
+    }
+
+    #[test]
+    fn test_game_ends_when_robots_are_stranded_without_energy() {
+        let mut simulation = battery_simulation();
+        let (base_x, base_y) = simulation.base_position;
+        simulation.map[base_y][(base_x + 20) % simulation.map_width] = Cell::Crystal;
+        simulation.energy_score = 0;
+        for robot in &mut simulation.robots {
+            robot.charge = Some(Battery::default().reserve);
+        }
+        // Un robot qui peut encore rapporter de l'énergie laisse la partie continuer
+        simulation.robots[0].charge = Some(Battery::default().capacity);
+        simulation.robots[0].x = (base_x + 10) % simulation.map_width;
+        simulation.step();
+        assert!(!simulation.game_over);
+
+        simulation.robots[0].charge = Some(0);
+        simulation.step();
+        assert!(simulation.game_over);
+        assert_eq!(simulation.end_reason, Some(EndReason::OutOfEnergy));
+    }
+
+    #[test]
+    fn test_wreck_is_towed_home_and_recharged() {
+        let mut simulation = battery_simulation();
+        let (base_x, base_y) = simulation.base_position;
+        simulation.robots.truncate(2);
+        simulation.robots[1].x = (base_x + 4) % simulation.map_width;
+        simulation.robots[1].charge = Some(0);
+        simulation.energy_score = 1;
+        // Une ressource hors de vue pour que la partie continue
+        simulation.map[base_y][(base_x + 20) % simulation.map_width] = Cell::Crystal;
+
+        for _ in 0..100 {
+            simulation.step();
+            if !simulation.robots[1].is_wreck() {
+                break;
+            }
+        }
+        // Rechargée dès son arrivée à la base
+        let wreck = &simulation.robots[1];
+        assert_eq!((wreck.x, wreck.y), (base_x, base_y));
+        assert_eq!(wreck.charge, Some(Battery::default().charge_per_energy));
+        assert_eq!(simulation.energy_score, 0);
+        assert_eq!(simulation.robots[0].rescuing, None);
+        assert!(!simulation.robots[0].towing);
+    }
+
+    #[test]
+    fn test_robot_collects_and_delivers_resource() {
+        let mut simulation = create_test_simulation();
+        let mut robot = Robot {
+            resource_coords: Some((2, 2)),
+            move_counter: Robot::default_speed(),
+            ..Robot::new(
+                simulation.base_position,
+                Role::Extractor,
+                Robot::default_speed(),
+            )
+        };
+
+        simulation.map[2][2] = Cell::ReservedCrystal;
+        robot.x = 2;
+        robot.y = 2;
+        simulation.update_robot(0, &mut robot, &[]);
+
+        assert_eq!(robot.carrying, Some(Cell::Crystal));
+        assert_eq!(simulation.map[2][2], Cell::Empty);
+
+        robot.x = 1;
+        robot.y = 1;
+        robot.move_counter = robot.speed;
+        simulation.update_robot(0, &mut robot, &[]);
+        robot.x = simulation.base_position.0;
+        robot.y = simulation.base_position.1;
+        robot.move_counter = robot.speed;
+        simulation.update_robot(0, &mut robot, &[]);
+
+        assert_eq!(simulation.crystal_score, 1);
+        assert_eq!(robot.role, Role::Explorer);
+        assert_eq!(robot.carrying, None);
+        assert_eq!(robot.resource_coords, None);
+    }
+
+    #[test]
+    fn test_robot_discovers_map() {
+        let mut simulation = create_test_simulation();
+        let mut robot = Robot {
+            move_counter: Robot::default_speed(),
+            ..Robot::new(
+                simulation.base_position,
+                Role::Explorer,
+                Robot::default_speed(),
+            )
+        };
+
+        simulation.update_robot(0, &mut robot, &[]);
+
+        assert!(simulation.discovered[robot.y][robot.x]);
+    }
+
+    fn radio_simulation(range: Option<usize>) -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            communication_range: range,
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    #[test]
+    fn test_isolated_robot_reports_when_back_in_range() {
+        let mut simulation = radio_simulation(Some(3));
+        let (base_x, base_y) = simulation.base_position;
+        simulation.robots.truncate(1);
+        let mut robot = simulation.robots[0].clone();
+        robot.x = (base_x + 10) % simulation.map_width;
+        robot.move_counter = robot.speed;
+
+        simulation.update_robot(0, &mut robot, &[]);
+        let seen = (robot.x, robot.y);
+        assert!(robot.knowledge[seen.1][seen.0]);
+        assert!(!simulation.discovered[seen.1][seen.0]);
+
+        // Trop loin de la base : elle n'apprend rien
+        simulation.robots[0] = robot;
+        simulation.communicate();
+        assert!(!simulation.discovered[seen.1][seen.0]);
+
+        simulation.robots[0].x = (base_x + 2) % simulation.map_width;
+        simulation.robots[0].y = base_y;
+        simulation.communicate();
+        assert!(simulation.discovered[seen.1][seen.0]);
+    }
+
+    #[test]
+    fn test_robots_in_range_merge_maps() {
+        let mut simulation = radio_simulation(Some(3));
+        let (base_x, base_y) = simulation.base_position;
+        simulation.robots.truncate(2);
+        let far = (base_x + 15) % simulation.map_width;
+        for (index, robot) in simulation.robots.iter_mut().enumerate() {
+            robot.x = far + index;
+            robot.y = base_y;
+            robot.knowledge[0][index] = true;
+        }
+
+        simulation.communicate();
+        for robot in &simulation.robots {
+            assert!(robot.knowledge[0][0] && robot.knowledge[0][1]);
+        }
+        assert!(!simulation.discovered[0][0] && !simulation.discovered[0][1]);
+    }
+
+    #[test]
+    fn test_large_map_path_stays_in_robot_map() {
+        let size = 256;
+        let mut simulation = Simulation::new(&SimulationConfig {
+            map_width: size,
+            map_height: size,
+            communication_range: Some(3),
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation.map = vec![vec![Cell::Empty; size]; size];
+        simulation.terrain = vec![vec![Terrain::Plain; size]; size];
+        // Le robot ne connaît qu'un U : aller de (5, 10) à (5, 50) oblige à en faire le tour
+        let mut knowledge = vec![vec![false; size]; size];
+        for y in [10, 50] {
+            knowledge[y][5..=100].fill(true);
+        }
+        for row in &mut knowledge[10..=50] {
+            row[100] = true;
+        }
+        let path = simulation
+            .find_path(0, (5, 10), (5, 50), true, &knowledge)
+            .unwrap();
+        assert_eq!(path.len(), 95 + 40 + 95 + 1);
+        assert!(path.iter().all(|&(x, y)| knowledge[y][x]));
+
+        // Le raccourci découvert par le robot remplace le détour
+        for y in 11..50 {
+            simulation.reveal(0, &mut knowledge, (5, y));
+        }
+        let path = simulation
+            .find_path(0, (5, 10), (5, 50), true, &knowledge)
+            .unwrap();
+        assert_eq!(path.len(), 41);
+        assert!(!simulation.discovered[30][5]);
+    }
+
+    #[test]
+    fn test_unlimited_range_shares_base_map() {
+        let mut simulation = radio_simulation(None);
+        for _ in 0..100 {
+            simulation.step();
+        }
+        for (index, robot) in simulation.robots.iter().enumerate() {
+            assert!(robot.knowledge.is_empty());
+            assert_eq!(simulation.knowledge(index), &simulation.discovered[..]);
+        }
+    }
+
+    #[test]
+    fn test_step_without_context() {
+        let mut simulation = create_test_simulation();
+        for _ in 0..100 {
+            simulation.step();
+        }
+        assert!(simulation.discovered.iter().flatten().any(|&d| d));
+    }
+
+    fn score_timeline(seed: u64, ticks: usize) -> Vec<(u32, u32)> {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            seed,
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        (0..ticks)
+            .map(|_| {
+                simulation.step();
+                (simulation.crystal_score, simulation.energy_score)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_score_timeline() {
+        for seed in [0, 1, 42] {
+            assert_eq!(score_timeline(seed, 2000), score_timeline(seed, 2000));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_map() {
+        let config = SimulationConfig {
+            seed: 7,
+            ..SimulationConfig::default()
+        };
+        let first = Simulation::new(&config).unwrap();
+        let second = Simulation::new(&config).unwrap();
+        assert_eq!(first.map, second.map);
+        assert_eq!(first.base_position, second.base_position);
+    }
+
+    #[test]
+    fn test_config_sizes_simulation() {
+        let simulation = Simulation::new(&SimulationConfig {
+            map_width: 60,
+            map_height: 20,
+            robots: vec![RobotConfig::default(); 5],
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        assert_eq!(simulation.map.len(), 20);
+        assert!(simulation.map.iter().all(|row| row.len() == 60));
+        assert_eq!(simulation.robots.len(), 5);
+    }
+
+    #[test]
+    fn test_max_ticks_ends_game() {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            end: EndConditions {
+                max_ticks: Some(10),
+                ..EndConditions::default()
+            },
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        while !simulation.game_over {
+            simulation.step();
+        }
+        assert_eq!(simulation.tick, 10);
+        assert_eq!(simulation.end_reason, Some(EndReason::MaxTicks));
+    }
+
+    #[test]
+    fn test_stats_count_every_robot_action() {
+        let mut simulation = create_test_simulation();
+        simulation.verbose = false;
+        for _ in 0..500 {
+            simulation.step();
+        }
+        let actions = simulation.stats.distance_travelled + simulation.stats.idle_ticks;
+        assert!(simulation.stats.distance_travelled > 0);
+        assert!(actions <= 500 * simulation.robots.len() as u64);
+    }
+
+    #[test]
+    fn test_step_reports_robot_actions() {
+        let mut simulation = create_test_simulation();
+        simulation.verbose = false;
+        let mut moves = 0;
+        for _ in 0..200 {
+            let before: Vec<(usize, usize)> =
+                simulation.robots.iter().map(|r| (r.x, r.y)).collect();
+            let actions = simulation.step();
+            assert_eq!(actions.len(), simulation.robots.len());
+            for ((action, robot), (x, y)) in actions.iter().zip(&simulation.robots).zip(before) {
+                if let RobotAction::Move(dx, dy) = action {
+                    moves += 1;
+                    assert_eq!(dx.abs() + dy.abs(), 1);
+                    assert_eq!(
+                        simulation.neighbour((x, y), (*dx, *dy)),
+                        Some((robot.x, robot.y))
+                    );
+                }
+            }
+        }
+        assert_eq!(moves, simulation.stats.distance_travelled);
+    }
+
+    #[test]
+    fn test_simulation_from_ascii_layout() {
+        let mut config = SimulationConfig::default();
+        config
+            .set_map_layout("#####\n#B.C#\n#####\n".to_string())
+            .unwrap();
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        assert_eq!((simulation.map_width, simulation.map_height), (5, 3));
+        assert_eq!(simulation.base_position, (1, 1));
+        assert_eq!(simulation.map[1][3], Cell::Crystal);
+        assert!(simulation.robots.iter().all(|r| (r.x, r.y) == (1, 1)));
+
+        while !simulation.game_over {
+            simulation.step();
+        }
+        assert_eq!(simulation.map[1][3], Cell::Empty);
+        assert_eq!(
+            simulation.end_reason,
+            Some(EndReason::AllResourcesCollected)
+        );
+    }
+
+    #[test]
+    fn test_wrapped_delta() {
+        assert_eq!(wrapped_delta(3, 4, 40), 1);
+        assert_eq!(wrapped_delta(0, 39, 40), -1);
+        assert_eq!(wrapped_delta(39, 0, 40), 1);
+    }
+
+    #[test]
+    fn test_state_hash_tracks_state() {
+        let mut first = create_test_simulation();
+        let second = create_test_simulation();
+        assert_eq!(first.state_hash(), second.state_hash());
+        first.verbose = false;
+        first.step();
+        assert_ne!(first.state_hash(), second.state_hash());
+    }
+
+    #[test]
+    fn test_too_many_resources_for_generated_map() {
+        // Le bruit laisse très peu de cases libres sur cette carte
+        let result = Simulation::new(&SimulationConfig {
+            noise_threshold: -0.5,
+            base_clear_radius: 0,
+            energy_count: 500,
+            crystal_count: 0,
+            ..SimulationConfig::default()
+        });
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_place_randomly_without_enough_free_cells() {
+        let mut map = vec![vec![Cell::Obstacle; 3]; 3];
+        map[1][1] = Cell::Empty;
+        let mut rng = StdRng::seed_from_u64(0);
+        let result = place_randomly(&mut map, &mut rng, Cell::Energy, 2);
+        assert_eq!(result, None);
+        assert_eq!(map[1][1], Cell::Empty);
+    }
+}
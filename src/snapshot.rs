@@ -0,0 +1,332 @@
+use crate::behaviour::Strategies;
+use crate::sensor;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Version du format, à incrémenter quand un snapshot existant ne peut plus être lu. Un champ
+// ajouté à Simulation ou à Robot a une valeur par défaut (serde(default)) qui reprend l'ancien
+// comportement : les snapshots écrits avant lui restent lisibles sans changer de version
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    Version(u32),
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "accès au snapshot impossible: {}", err),
+            SnapshotError::Format(err) => write!(f, "snapshot illisible: {}", err),
+            SnapshotError::Version(version) => write!(
+                f,
+                "snapshot en version {} alors que cette version du jeu lit la version {}",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Invalid(message) => write!(f, "snapshot incohérent: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    simulation: &'a Simulation,
+}
+
+// La version est lue avant le reste pour pouvoir refuser un ancien format proprement
+#[derive(Deserialize)]
+struct SnapshotFile {
+    version: u32,
+    simulation: serde_json::Value,
+}
+
+pub fn to_string(simulation: &Simulation) -> Result<String, SnapshotError> {
+    serde_json::to_string(&SnapshotRef {
+        version: SNAPSHOT_VERSION,
+        simulation,
+    })
+    .map_err(SnapshotError::Format)
+}
+
+pub fn from_str(content: &str) -> Result<Simulation, SnapshotError> {
+    from_str_with_strategies(content, Strategies::default())
+}
+
+// Le snapshot ne garde que le nom des stratégies enregistrées : il faut les redonner
+pub fn from_str_with_strategies(
+    content: &str,
+    strategies: Strategies,
+) -> Result<Simulation, SnapshotError> {
+    let file: SnapshotFile = serde_json::from_str(content).map_err(SnapshotError::Format)?;
+    if file.version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::Version(file.version));
+    }
+    let mut simulation: Simulation =
+        serde_json::from_value(file.simulation).map_err(SnapshotError::Format)?;
+    simulation.strategies = strategies;
+    check_consistency(&simulation)?;
+    Ok(simulation)
+}
+
+// Les deux simulations sont dans le même état pour un snapshot. Les champs qu'il ne garde pas
+// ne sont pas comparés : les caches reconstruits à la demande (hiérarchies, planificateurs,
+// champ de distances), les stratégies enregistrées et `verbose`
+pub fn same_state(a: &Simulation, b: &Simulation) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+pub fn save(simulation: &Simulation, path: &Path) -> Result<(), SnapshotError> {
+    fs::write(path, to_string(simulation)?).map_err(SnapshotError::Io)
+}
+
+pub fn load(path: &Path) -> Result<Simulation, SnapshotError> {
+    load_with_strategies(path, Strategies::default())
+}
+
+pub fn load_with_strategies(
+    path: &Path,
+    strategies: Strategies,
+) -> Result<Simulation, SnapshotError> {
+    from_str_with_strategies(
+        &fs::read_to_string(path).map_err(SnapshotError::Io)?,
+        strategies,
+    )
+}
+
+fn grid_has_size<T>(grid: &[Vec<T>], width: usize, height: usize) -> bool {
+    grid.len() == height && grid.iter().all(|row| row.len() == width)
+}
+
+fn check_consistency(simulation: &Simulation) -> Result<(), SnapshotError> {
+    let (width, height) = (simulation.map_width, simulation.map_height);
+    if !grid_has_size(&simulation.map, width, height) {
+        return Err(SnapshotError::Invalid(format!(
+            "la carte ne fait pas {}x{} cases",
+            width, height
+        )));
+    }
+    if !grid_has_size(&simulation.terrain, width, height) {
+        return Err(SnapshotError::Invalid(format!(
+            "le terrain ne fait pas {}x{} cases",
+            width, height
+        )));
+    }
+    if !grid_has_size(&simulation.discovered, width, height) {
+        return Err(SnapshotError::Invalid(format!(
+            "le brouillard ne fait pas {}x{} cases",
+            width, height
+        )));
+    }
+    if !simulation.visible.is_empty() && !grid_has_size(&simulation.visible, width, height) {
+        return Err(SnapshotError::Invalid(format!(
+            "le champ de vision ne fait pas {}x{} cases",
+            width, height
+        )));
+    }
+    let in_bounds = |(x, y): (usize, usize)| x < width && y < height;
+    if !in_bounds(simulation.base_position) {
+        return Err(SnapshotError::Invalid(format!(
+            "base hors de la carte en {:?}",
+            simulation.base_position
+        )));
+    }
+    if let Some(battery) = &simulation.battery {
+        battery.validate().map_err(SnapshotError::Invalid)?;
+    }
+    for (name, sensor) in &simulation.sensors {
+        sensor.validate(name).map_err(SnapshotError::Invalid)?;
+    }
+    for robot in &simulation.robots {
+        if !in_bounds((robot.x, robot.y)) || !robot.resource_coords.is_none_or(in_bounds) {
+            return Err(SnapshotError::Invalid(format!(
+                "robot hors de la carte en {:?}",
+                (robot.x, robot.y)
+            )));
+        }
+        if !robot.path.iter().copied().all(in_bounds) || !robot.frontier.is_none_or(in_bounds) {
+            return Err(SnapshotError::Invalid(format!(
+                "chemin ou frontière du robot en {:?} hors de la carte",
+                (robot.x, robot.y)
+            )));
+        }
+        if simulation.strategies.get(&robot.strategy).is_none()
+            && !simulation.behaviour_trees.contains_key(&robot.strategy)
+            && !simulation.scripts.contains_key(&robot.strategy)
+            && !simulation.brains.contains_key(&robot.strategy)
+        {
+            return Err(SnapshotError::Invalid(format!(
+                "stratégie de robot '{}' inconnue",
+                robot.strategy
+            )));
+        }
+        if robot.sensor != sensor::DEFAULT_SENSOR && !simulation.sensors.contains_key(&robot.sensor)
+        {
+            return Err(SnapshotError::Invalid(format!(
+                "capteur de robot '{}' inconnu",
+                robot.sensor
+            )));
+        }
+        // Une charge par robot avec un modèle de batterie, aucune sans
+        let charge_valid = match (simulation.battery, robot.charge) {
+            (Some(battery), Some(charge)) => charge <= battery.capacity,
+            (None, None) => true,
+            _ => false,
+        };
+        if !charge_valid
+            || robot
+                .rescuing
+                .is_some_and(|wreck| wreck >= simulation.robots.len())
+        {
+            return Err(SnapshotError::Invalid(
+                "batterie d'un robot incohérente avec le scénario".to_string(),
+            ));
+        }
+        // Une carte par robot avec une portée radio, aucune sans
+        let knowledge_valid = match simulation.communication_range {
+            Some(_) => grid_has_size(&robot.knowledge, width, height),
+            None => robot.knowledge.is_empty(),
+        };
+        if !knowledge_valid {
+            return Err(SnapshotError::Invalid(
+                "carte d'un robot incohérente avec la portée radio".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimulationConfig;
+
+    fn running_simulation(ticks: usize) -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig::default()).unwrap();
+        simulation.verbose = false;
+        for _ in 0..ticks {
+            simulation.step();
+        }
+        simulation
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_resumes_identically() {
+        let mut original = running_simulation(400);
+        let mut restored = from_str(&to_string(&original).unwrap()).unwrap();
+        restored.verbose = false;
+        assert!(same_state(&restored, &original));
+
+        for _ in 0..1000 {
+            original.step();
+            restored.step();
+        }
+        assert!(same_state(&restored, &original));
+        original.step();
+        assert!(!same_state(&restored, &original));
+    }
+
+    // Snapshot de la version 2 écrit avant l'ajout des champs qui ont une valeur par défaut
+    #[test]
+    fn test_snapshot_without_later_fields_loads() {
+        let mut content = serde_json::to_value(SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            simulation: &running_simulation(50),
+        })
+        .unwrap();
+        let simulation = content["simulation"].as_object_mut().unwrap();
+        for field in [
+            "topology",
+            "movement",
+            "exploration",
+            "communication_range",
+            "battery",
+            "behaviour_trees",
+            "scripts",
+            "brains",
+            "sensors",
+            "visible",
+        ] {
+            assert!(simulation.remove(field).is_some(), "{}", field);
+        }
+        for robot in simulation["robots"].as_array_mut().unwrap() {
+            let robot = robot.as_object_mut().unwrap();
+            for field in [
+                "path",
+                "frontier",
+                "strategy",
+                "sensor",
+                "active_node",
+                "script_error",
+                "knowledge",
+                "charge",
+                "rescuing",
+                "towing",
+            ] {
+                assert!(robot.remove(field).is_some(), "{}", field);
+            }
+        }
+        let mut restored = from_str(&content.to_string()).unwrap();
+        restored.verbose = false;
+        // Action en cours enregistrée par son seul nom
+        let mut content = serde_json::to_value(SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            simulation: &restored,
+        })
+        .unwrap();
+        content["simulation"]["robots"][0]["active_node"] = "scan".into();
+        assert_eq!(
+            from_str(&content.to_string()).unwrap().robots[0].active_node,
+            None
+        );
+        for _ in 0..100 {
+            restored.step();
+        }
+    }
+
+    #[test]
+    fn test_snapshot_version_is_checked() {
+        let content = to_string(&running_simulation(0)).unwrap().replacen(
+            &format!("\"version\":{}", SNAPSHOT_VERSION),
+            "\"version\":99",
+            1,
+        );
+        assert!(matches!(
+            from_str(&content),
+            Err(SnapshotError::Version(99))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_consistency_is_checked() {
+        let mut simulation = running_simulation(0);
+        simulation.robots[0].x = simulation.map_width;
+        let err = from_str(&to_string(&simulation).unwrap()).unwrap_err();
+        assert!(matches!(err, SnapshotError::Invalid(_)));
+
+        let mut simulation = running_simulation(0);
+        simulation.robots[1].strategy = "inconnue".to_string();
+        let err = from_str(&to_string(&simulation).unwrap()).unwrap_err();
+        assert!(matches!(err, SnapshotError::Invalid(_)));
+
+        let mut simulation = running_simulation(0);
+        simulation.robots[0]
+            .path
+            .push_back((0, simulation.map_height));
+        let err = from_str(&to_string(&simulation).unwrap()).unwrap_err();
+        assert!(matches!(err, SnapshotError::Invalid(_)));
+
+        let mut simulation = running_simulation(0);
+        simulation.robots[0].frontier = Some((simulation.map_width, 0));
+        let err = from_str(&to_string(&simulation).unwrap()).unwrap_err();
+        assert!(matches!(err, SnapshotError::Invalid(_)));
+    }
+}
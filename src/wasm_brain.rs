@@ -0,0 +1,521 @@
+use crate::behaviour::{Command, WorldView};
+use crate::behaviour_tree::{self, Action};
+use crate::config::ConfigError;
+use crate::simulation::{Cell, Robot, Role};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use wasmi::core::TrapCode;
+use wasmi::{
+    Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+// Carburant (à peu près une instruction chacun) qu'un module peut brûler par tick
+pub const FUEL_PER_TICK: u64 = 100_000;
+// Mémoire linéaire maximale d'un module, en octets
+pub const MEMORY_LIMIT: usize = 1 << 20;
+// Rayon de la grille de capteurs centrée sur le robot
+pub const SENSOR_RADIUS: isize = 4;
+const SENSOR_SIDE: usize = 2 * SENSOR_RADIUS as usize + 1;
+
+// Codes des cases de la grille de capteurs
+const SENSOR_UNKNOWN: u8 = 0;
+const SENSOR_EMPTY: u8 = 1;
+const SENSOR_OBSTACLE: u8 = 2;
+const SENSOR_ENERGY: u8 = 3;
+const SENSOR_CRYSTAL: u8 = 4;
+const SENSOR_BASE: u8 = 5;
+const SENSOR_RESERVED: u8 = 6;
+
+// Action émise par le module pendant le tick, la dernière appelée l'emporte
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Emitted {
+    Wait,
+    Step(i32, i32),
+    MoveTo(i32, i32),
+    Action(Action),
+}
+
+// Données du robot lues par le module pendant un tick
+#[derive(Default)]
+struct HostState {
+    robot: [i32; 8],
+    sensors: Vec<u8>,
+    emitted: Option<Emitted>,
+    limits: StoreLimits,
+}
+
+struct Compiled {
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+}
+
+// Contrôleur de robot compilé en WebAssembly, dans n'importe quel langage. Le module
+// exporte `tick` et sa mémoire `memory`, et importe du module "robot" les fonctions
+// de l'ABI ci-dessous. Il est réinstancié à chaque tick : sa mémoire ne persiste pas,
+// ce qui garde les snapshots et les replays reproductibles
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+pub struct WasmBrain {
+    wasm: Vec<u8>,
+    compiled: Arc<Compiled>,
+}
+
+impl fmt::Debug for WasmBrain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WasmBrain")
+            .field("size", &self.wasm.len())
+            .finish()
+    }
+}
+
+impl PartialEq for WasmBrain {
+    fn eq(&self, other: &WasmBrain) -> bool {
+        self.wasm == other.wasm
+    }
+}
+
+impl TryFrom<Vec<u8>> for WasmBrain {
+    type Error = String;
+
+    fn try_from(wasm: Vec<u8>) -> Result<WasmBrain, String> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &wasm).map_err(|err| err.to_string())?;
+        if !matches!(module.get_export("tick"), Some(wasmi::ExternType::Func(_))) {
+            return Err("le module n'exporte pas de fonction tick".to_string());
+        }
+        let linker = linker(&engine).map_err(|err| err.to_string())?;
+        // Instanciation à vide : les imports inconnus et la mémoire initiale trop grande
+        // sont refusés dès le chargement
+        linker
+            .instantiate(&mut store(&engine, HostState::default()), &module)
+            .map_err(|err| err.to_string())?;
+        Ok(WasmBrain {
+            wasm,
+            compiled: Arc::new(Compiled {
+                engine,
+                module,
+                linker,
+            }),
+        })
+    }
+}
+
+impl From<WasmBrain> for Vec<u8> {
+    fn from(brain: WasmBrain) -> Vec<u8> {
+        brain.wasm
+    }
+}
+
+// Copie des octets de l'hôte dans la mémoire du module, -1 si elle n'y tient pas
+fn write_memory(caller: &mut Caller<HostState>, pointer: i32, bytes: &[u8]) -> i32 {
+    let Some(memory) = caller.get_export("memory").and_then(Extern::into_memory) else {
+        return -1;
+    };
+    match usize::try_from(pointer) {
+        Ok(offset) if memory.write(caller, offset, bytes).is_ok() => 0,
+        _ => -1,
+    }
+}
+
+// ABI du module "robot" :
+// - read_robot(ptr) -> i32 : écrit 8 entiers i32 little-endian à ptr : x, y, rôle
+//   (0 explorateur, 1 extracteur), chargé (0 ou 1), x et y de la ressource repérée
+//   (-1 sans ressource), x et y de la base. Renvoie 0, ou -1 si la mémoire est trop petite
+// - read_sensors(ptr) -> i32 : écrit la grille de capteurs, un octet par case, ligne par
+//   ligne, centrée sur le robot. Renvoie la taille du côté, ou -1
+// - wait(), step(dx, dy), move_to(dx, dy) (déplacements relatifs au robot), pick_up(),
+//   deposit(), explore(), return_to_base() : action du tick
+fn linker(engine: &Engine) -> Result<Linker<HostState>, wasmi::errors::LinkerError> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap(
+            "robot",
+            "read_robot",
+            |mut caller: Caller<HostState>, pointer: i32| {
+                let bytes: Vec<u8> = caller
+                    .data()
+                    .robot
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect();
+                write_memory(&mut caller, pointer, &bytes)
+            },
+        )?
+        .func_wrap(
+            "robot",
+            "read_sensors",
+            |mut caller: Caller<HostState>, pointer: i32| {
+                let bytes = caller.data().sensors.clone();
+                match write_memory(&mut caller, pointer, &bytes) {
+                    0 => SENSOR_SIDE as i32,
+                    error => error,
+                }
+            },
+        )?;
+    let actions: [(&str, Emitted); 5] = [
+        ("wait", Emitted::Wait),
+        ("pick_up", Emitted::Action(Action::PickUp)),
+        ("deposit", Emitted::Action(Action::Deposit)),
+        ("explore", Emitted::Action(Action::Explore)),
+        ("return_to_base", Emitted::Action(Action::MoveToBase)),
+    ];
+    for (name, emitted) in actions {
+        linker.func_wrap("robot", name, move |mut caller: Caller<HostState>| {
+            caller.data_mut().emitted = Some(emitted);
+        })?;
+    }
+    linker
+        .func_wrap(
+            "robot",
+            "step",
+            |mut caller: Caller<HostState>, dx: i32, dy: i32| {
+                caller.data_mut().emitted = Some(Emitted::Step(dx, dy));
+            },
+        )?
+        .func_wrap(
+            "robot",
+            "move_to",
+            |mut caller: Caller<HostState>, dx: i32, dy: i32| {
+                caller.data_mut().emitted = Some(Emitted::MoveTo(dx, dy));
+            },
+        )?;
+    Ok(linker)
+}
+
+fn sensor_code(cell: &Cell) -> u8 {
+    match cell {
+        Cell::Empty => SENSOR_EMPTY,
+        Cell::Obstacle => SENSOR_OBSTACLE,
+        Cell::Energy => SENSOR_ENERGY,
+        Cell::Crystal => SENSOR_CRYSTAL,
+        Cell::Base => SENSOR_BASE,
+        Cell::ReservedEnergy | Cell::ReservedCrystal => SENSOR_RESERVED,
+    }
+}
+
+// Cases découvertes autour du robot, inconnues dans le brouillard et hors de la carte
+fn sensors(robot: &Robot, world: &WorldView) -> Vec<u8> {
+    let simulation = world.simulation;
+    (-SENSOR_RADIUS..=SENSOR_RADIUS)
+        .flat_map(|dy| (-SENSOR_RADIUS..=SENSOR_RADIUS).map(move |dx| (dx, dy)))
+        .map(|delta| {
+            simulation
+                .topology
+                .neighbour(
+                    (robot.x, robot.y),
+                    delta,
+                    simulation.map_width,
+                    simulation.map_height,
+                )
+                .filter(|&(x, y)| world.known[y][x])
+                .map_or(SENSOR_UNKNOWN, |(x, y)| sensor_code(&simulation.map[y][x]))
+        })
+        .collect()
+}
+
+fn robot_state(robot: &Robot, world: &WorldView) -> [i32; 8] {
+    let (resource_x, resource_y) = robot
+        .resource_coords
+        .map_or((-1, -1), |(x, y)| (x as i32, y as i32));
+    let (base_x, base_y) = world.simulation.base_position;
+    [
+        robot.x as i32,
+        robot.y as i32,
+        match robot.role {
+            Role::Explorer => 0,
+            Role::Extractor => 1,
+        },
+        robot.carrying.is_some() as i32,
+        resource_x,
+        resource_y,
+        base_x as i32,
+        base_y as i32,
+    ]
+}
+
+fn store(engine: &Engine, state: HostState) -> Store<HostState> {
+    let mut store = Store::new(
+        engine,
+        HostState {
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+            ..state
+        },
+    );
+    store.limiter(|state| &mut state.limits);
+    store
+        .set_fuel(FUEL_PER_TICK)
+        .expect("carburant activé à la compilation");
+    store
+}
+
+fn run_error(err: wasmi::Error) -> String {
+    match err.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => {
+            format!("carburant épuisé ({} par tick)", FUEL_PER_TICK)
+        }
+        _ => err.to_string(),
+    }
+}
+
+impl WasmBrain {
+    // Fichier .wasm, ou .wat pour le format texte
+    pub fn load(path: &Path) -> Result<WasmBrain, ConfigError> {
+        let invalid = |err: String| {
+            ConfigError::Invalid(format!("module {} invalide: {}", path.display(), err))
+        };
+        let wasm = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("wat") => wat::parse_file(path).map_err(|err| invalid(err.to_string()))?,
+            _ => std::fs::read(path).map_err(ConfigError::Io)?,
+        };
+        WasmBrain::try_from(wasm).map_err(invalid)
+    }
+
+    // Un module par fichier .wasm ou .wat du dossier, nommé d'après le fichier
+    pub fn load_dir(dir: &Path) -> Result<BTreeMap<String, WasmBrain>, ConfigError> {
+        let mut brains = BTreeMap::new();
+        for entry in std::fs::read_dir(dir).map_err(ConfigError::Io)? {
+            let path = entry.map_err(ConfigError::Io)?.path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if !matches!(extension, Some("wasm" | "wat")) {
+                continue;
+            }
+            if brains
+                .insert(name.to_string(), WasmBrain::load(&path)?)
+                .is_some()
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "plusieurs modules nommés '{}' dans {}",
+                    name,
+                    dir.display()
+                )));
+            }
+        }
+        Ok(brains)
+    }
+
+    pub fn decide(
+        &self,
+        robot: &Robot,
+        world: &WorldView,
+        rng: &mut dyn RngCore,
+    ) -> Result<Command, String> {
+        let compiled = &*self.compiled;
+        let mut store = store(
+            &compiled.engine,
+            HostState {
+                robot: robot_state(robot, world),
+                sensors: sensors(robot, world),
+                ..HostState::default()
+            },
+        );
+        let instance = compiled
+            .linker
+            .instantiate(&mut store, &compiled.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(run_error)?;
+        instance
+            .get_typed_func::<(), ()>(&store, "tick")
+            .and_then(|tick| tick.call(&mut store, ()))
+            .map_err(run_error)?;
+        command(store.data().emitted, robot, world, rng)
+    }
+}
+
+fn command(
+    emitted: Option<Emitted>,
+    robot: &Robot,
+    world: &WorldView,
+    rng: &mut dyn RngCore,
+) -> Result<Command, String> {
+    let simulation = world.simulation;
+    let relative = |dx: i32, dy: i32| {
+        simulation
+            .topology
+            .neighbour(
+                (robot.x, robot.y),
+                (dx as isize, dy as isize),
+                simulation.map_width,
+                simulation.map_height,
+            )
+            .ok_or_else(|| format!("déplacement ({}, {}) hors de la carte", dx, dy))
+    };
+    Ok(match emitted {
+        None | Some(Emitted::Wait) => Command::Stay,
+        Some(Emitted::Step(dx, dy)) if dx.abs() > 1 || dy.abs() > 1 => {
+            return Err(format!("pas ({}, {}) trop long", dx, dy))
+        }
+        Some(Emitted::Step(dx, dy)) => Command::Step(relative(dx, dy)?),
+        Some(Emitted::MoveTo(dx, dy)) => Command::GoTo {
+            target: relative(dx, dy)?,
+            avoid_fog: false,
+        },
+        Some(Emitted::Action(action)) => {
+            behaviour_tree::command(action, robot, world, rng).unwrap_or(Command::Stay)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RobotConfig, SimulationConfig};
+    use crate::simulation::Simulation;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+
+    const IMPORTS: &str = r#"
+        (import "robot" "read_robot" (func $read_robot (param i32) (result i32)))
+        (import "robot" "read_sensors" (func $read_sensors (param i32) (result i32)))
+        (import "robot" "step" (func $step (param i32 i32)))
+        (import "robot" "move_to" (func $move_to (param i32 i32)))
+        (import "robot" "explore" (func $explore))
+    "#;
+
+    fn brain(body: &str) -> WasmBrain {
+        let source = format!("(module {} {})", IMPORTS, body);
+        WasmBrain::try_from(wat::parse_str(source).unwrap()).unwrap()
+    }
+
+    fn simulation(brain: WasmBrain) -> Simulation {
+        let mut simulation = Simulation::new(&SimulationConfig {
+            robots: vec![RobotConfig {
+                strategy: "wasm".to_string(),
+                ..RobotConfig::default()
+            }],
+            brains: [("wasm".to_string(), brain)].into(),
+            ..SimulationConfig::default()
+        })
+        .unwrap();
+        simulation.verbose = false;
+        simulation
+    }
+
+    fn decide(brain: &WasmBrain, simulation: &Simulation) -> Result<Command, String> {
+        let world = WorldView {
+            simulation,
+            known: &simulation.discovered,
+            claimed_frontiers: &[],
+        };
+        brain.decide(
+            &simulation.robots[0],
+            &world,
+            &mut ChaCha12Rng::seed_from_u64(0),
+        )
+    }
+
+    #[test]
+    fn test_module_emits_actions() {
+        let simulation = simulation(brain(r#"(func (export "tick"))"#));
+        let (x, y) = simulation.base_position;
+        let wait = brain(r#"(func (export "tick"))"#);
+        assert_eq!(decide(&wait, &simulation), Ok(Command::Stay));
+        // La dernière action émise l'emporte
+        let step = brain(
+            r#"(func (export "tick") (call $explore) (call $step (i32.const 1) (i32.const 0)))"#,
+        );
+        assert_eq!(decide(&step, &simulation), Ok(Command::Step((x + 1, y))));
+        let move_to =
+            brain(r#"(func (export "tick") (call $move_to (i32.const -3) (i32.const 2)))"#);
+        assert_eq!(
+            decide(&move_to, &simulation),
+            Ok(Command::GoTo {
+                target: (x - 3, y + 2),
+                avoid_fog: false
+            })
+        );
+        let long_step = brain(r#"(func (export "tick") (call $step (i32.const 2) (i32.const 0)))"#);
+        assert!(decide(&long_step, &simulation).is_err());
+    }
+
+    #[test]
+    fn test_module_reads_robot_and_sensors() {
+        // Avance vers la droite si la case de droite est une base (code 5) vue par les
+        // capteurs et que le robot est à l'abscisse de la base
+        let reader = brain(
+            r#"(memory (export "memory") 1)
+            (func (export "tick")
+                (drop (call $read_robot (i32.const 0)))
+                (drop (call $read_sensors (i32.const 64)))
+                (if (i32.and
+                        (i32.eq (i32.load (i32.const 0)) (i32.load (i32.const 24)))
+                        (i32.eq (i32.load8_u (i32.const 105)) (i32.const 5)))
+                    (then (call $step (i32.const 1) (i32.const 0)))))"#,
+        );
+        let mut simulation = simulation(reader.clone());
+        assert_eq!(decide(&reader, &simulation), Ok(Command::Stay));
+        let (x, y) = simulation.base_position;
+        simulation.discovered[y][x + 1] = true;
+        simulation.map[y][x + 1] = Cell::Base;
+        assert_eq!(decide(&reader, &simulation), Ok(Command::Step((x + 1, y))));
+    }
+
+    #[test]
+    fn test_fuel_and_memory_limits() {
+        let runaway = brain(r#"(func (export "tick") (loop $forever (br $forever)))"#);
+        let mut simulation = simulation(runaway);
+        let position = simulation.base_position;
+        for _ in 0..4 {
+            simulation.step();
+        }
+        let robot = &simulation.robots[0];
+        assert_eq!((robot.x, robot.y), position);
+        assert_eq!(
+            robot.script_error.as_deref(),
+            Some("carburant épuisé (100000 par tick)")
+        );
+
+        // 32 pages de 64 Kio dépassent la limite de 1 Mio
+        let greedy = wat::parse_str(r#"(module (memory 32) (func (export "tick")))"#).unwrap();
+        assert!(WasmBrain::try_from(greedy).is_err());
+        let grower = brain(
+            r#"(memory (export "memory") 1)
+            (func (export "tick")
+                (if (i32.ge_s (memory.grow (i32.const 64)) (i32.const 0))
+                    (then (call $explore))))"#,
+        );
+        assert_eq!(decide(&grower, &simulation), Ok(Command::Stay));
+    }
+
+    #[test]
+    fn test_invalid_modules_are_rejected() {
+        assert!(WasmBrain::try_from(b"pas du wasm".to_vec()).is_err());
+        let without_tick = wat::parse_str("(module (func (export \"think\")))").unwrap();
+        assert!(WasmBrain::try_from(without_tick).is_err());
+        let unknown_import =
+            wat::parse_str(r#"(module (import "robot" "fly" (func)) (func (export "tick")))"#)
+                .unwrap();
+        assert!(WasmBrain::try_from(unknown_import).is_err());
+    }
+
+    #[test]
+    fn test_brains_directory_is_loaded() {
+        let brains = WasmBrain::load_dir(Path::new("brains")).unwrap();
+        assert!(brains.contains_key("collector"));
+
+        let config = SimulationConfig::load(Path::new("scenarios/wasm.toml")).unwrap();
+        let mut simulation = Simulation::new(&config).unwrap();
+        simulation.verbose = false;
+        while !simulation.game_over {
+            simulation.step();
+            assert!(simulation
+                .robots
+                .iter()
+                .all(|robot| robot.script_error.is_none()));
+        }
+        assert!(simulation.crystal_score + simulation.energy_score > 0);
+
+        let json = serde_json::to_string(&simulation).unwrap();
+        let loaded: Simulation = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.brains, simulation.brains);
+    }
+}
@@ -0,0 +1,69 @@
+use rand::RngCore;
+use rust_game::behaviour::{Behaviour, Command, Strategies, WorldView};
+use rust_game::config::SimulationConfig;
+use rust_game::simulation::{Robot, Simulation};
+use rust_game::snapshot;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Stratégie écrite hors de la crate : le robot ne bouge pas et compte ses décisions
+struct Sentinelle {
+    decisions: Arc<AtomicUsize>,
+}
+
+impl Behaviour for Sentinelle {
+    fn decide(&self, _robot: &Robot, _world: &WorldView, _rng: &mut dyn RngCore) -> Command {
+        self.decisions.fetch_add(1, Ordering::Relaxed);
+        Command::Stay
+    }
+}
+
+fn strategies(decisions: &Arc<AtomicUsize>) -> Strategies {
+    let mut strategies = Strategies::default();
+    strategies.register(
+        "sentinelle",
+        Box::new(Sentinelle {
+            decisions: Arc::clone(decisions),
+        }),
+    );
+    strategies
+}
+
+#[test]
+fn test_scenario_selects_registered_strategy() {
+    let dir = std::env::temp_dir().join(format!("rust_game_strategy_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sentinelle.toml");
+    fs::write(
+        &path,
+        "seed = 5\n\n[[robots]]\nrole = \"explorer\"\nstrategy = \"sentinelle\"\n",
+    )
+    .unwrap();
+
+    // Sans l'enregistrement, le scénario est refusé
+    assert!(SimulationConfig::load(&path).is_err());
+
+    let decisions = Arc::new(AtomicUsize::new(0));
+    let config = SimulationConfig::load_with_strategies(&path, strategies(&decisions)).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let mut simulation = Simulation::new(&config).unwrap();
+    for _ in 0..20 {
+        simulation.step();
+    }
+    assert!(decisions.load(Ordering::Relaxed) > 0);
+    let robot = &simulation.robots[0];
+    assert_eq!(robot.strategy, "sentinelle");
+    assert_eq!((robot.x, robot.y), simulation.base_position);
+
+    // Le snapshot garde le nom de la stratégie, pas son code
+    let content = snapshot::to_string(&simulation).unwrap();
+    assert!(snapshot::from_str(&content).is_err());
+    let mut restored =
+        snapshot::from_str_with_strategies(&content, strategies(&decisions)).unwrap();
+    let before = decisions.load(Ordering::Relaxed);
+    for _ in 0..5 {
+        restored.step();
+    }
+    assert!(decisions.load(Ordering::Relaxed) > before);
+}